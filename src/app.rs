@@ -3,43 +3,157 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use std::{
+    collections::HashMap,
+    ffi::OsString,
     fs::{self, File},
-    io::{self, BufReader, IsTerminal},
+    io::{self, IsTerminal, Seek},
+    iter,
+    num::NonZeroUsize,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError, mpsc},
+    thread,
+    time::Instant,
 };
 
-use anyhow::{Context, bail};
 use byte_unit::{Byte, UnitType};
 use clap::Parser;
 use log::{info, warn};
-use simplelog::{ColorChoice, Config, SimpleLogger, TermLogger, TerminalMode};
 use zopfli::{Format, Options};
 
-use crate::{cli::Opt, input::Input, output::Output};
+use crate::{
+    budget::{self, Budget, Violation},
+    cache::Cache,
+    cli::{HashAlgorithm, Opt, Overwrite},
+    decoder, encoder,
+    error::Error,
+    hash::{self, HashReader, HashWriter, Hasher},
+    input::{self, Input},
+    jobserver, logger,
+    magic::Compressed,
+    manifest::Manifest,
+    output::{self, Output},
+    overwrite, priority,
+    signal::{self, PartialOutput},
+    throttle::Throttle,
+    walk,
+    watch::{self, Change},
+};
+
+/// Settings for compressing each file.
+#[derive(Debug)]
+struct Settings<'a> {
+    opt: &'a Opt,
+    options: Options,
+    format: Format,
+    extension: &'a str,
+    chunk_size: usize,
+    jobserver: Option<Arc<jobserver::Client>>,
+    overwrite: Option<Overwrite>,
+    cache: Option<Cache>,
+    budgets: Vec<Budget>,
+}
+
+/// The result of compressing a file.
+#[derive(Debug)]
+struct Outcome {
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    input_digest: Vec<u8>,
+    output_digest: Vec<u8>,
+    input_size: Option<u64>,
+    output_size: Option<u64>,
+    skipped: bool,
+}
+
+impl Outcome {
+    /// Returns the outcome of `input` which has been skipped.
+    fn skipped(input: Option<&Path>, output: Option<PathBuf>) -> Self {
+        Self {
+            input: input.map(Path::to_path_buf),
+            output,
+            input_digest: Vec::new(),
+            output_digest: Vec::new(),
+            input_size: None,
+            output_size: None,
+            skipped: true,
+        }
+    }
+}
 
-/// Runs the program and returns the result.
-#[allow(clippy::cognitive_complexity, clippy::too_many_lines)]
-pub fn run() -> anyhow::Result<()> {
-    let opt = Opt::parse();
+/// Runs the program with the command-line arguments `args` and returns the
+/// result.
+#[allow(clippy::too_many_lines)]
+pub fn run(args: impl IntoIterator<Item = OsString>) -> Result<(), Error> {
+    let opt = Opt::parse_from(args);
 
     if let Some(shell) = opt.generate_completion {
         Opt::print_completion(shell);
         return Ok(());
     }
+    if opt.list_compressed_types {
+        for kind in Compressed::ALL {
+            println!("{kind}");
+        }
+        return Ok(());
+    }
+    let jobs = match opt.jobs {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        n => n,
+    };
+    // The jobserver is connected before any file is opened, since its file
+    // descriptors may be reused otherwise.
+    let jobserver = (jobs > 1).then(jobserver::Client::from_env).flatten();
 
-    let log_level = opt.log_level.into();
-    TermLogger::init(
-        log_level,
-        Config::default(),
-        TerminalMode::Stderr,
-        ColorChoice::Auto,
-    )
-    .or_else(|_| SimpleLogger::init(log_level, Config::default()))?;
-
-    let zopfli_opt = Options {
-        iteration_count: opt.iteration,
-        ..Default::default()
+    let log_file = opt
+        .log_file
+        .as_ref()
+        .map(|path| {
+            File::options()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| Error::io(format!("could not open {}", path.display()), err))
+        })
+        .transpose()?;
+    logger::init(opt.log_level.clone().into(), opt.log_format, log_file)
+        .expect("the logger should only be initialized once");
+    if opt.cache_stats || opt.cache_clear {
+        return run_cache(&opt);
+    }
+    let (jobserver, jobs) = match jobserver {
+        Some(Ok(client)) => (Some(Arc::new(client)), jobs),
+        // If the jobserver is specified but not available, files are compressed
+        // one at a time so as not to exceed the limit of make.
+        Some(Err(err)) => {
+            warn!(
+                "could not connect to the jobserver, so files are compressed one at a time: {err}"
+            );
+            (None, 1)
+        }
+        None => (None, jobs),
     };
-    let format = opt.format.into();
+    // These are set before any other thread is spawned, so that the threads
+    // inherit them.
+    if let Some(niceness) = opt.nice {
+        priority::set_niceness(niceness)
+            .map_err(|err| Error::io("could not set the niceness", err))?;
+    }
+    if let Some(class) = opt.io_class {
+        priority::set_io_class(class)
+            .map_err(|err| Error::io("could not set the I/O scheduling class", err))?;
+    }
+    if let Err(err) = signal::install() {
+        warn!("could not install signal handlers: {err}");
+    }
+    if let Some(ref client) = jobserver {
+        signal::release_tokens_of(Arc::clone(client));
+    }
+
+    let mut options = encoder::level_options(opt.level.get().unwrap_or(encoder::DEFAULT_LEVEL));
+    if let Some(iteration_count) = opt.iteration {
+        options.iteration_count = iteration_count;
+    }
+    let format = opt.format.clone().into();
     #[allow(clippy::option_if_let_else)]
     let extension = if let Some(ref suffix) = opt.suffix {
         suffix
@@ -54,85 +168,1508 @@ pub fn run() -> anyhow::Result<()> {
         warn!("the suffix is an empty string");
     }
 
-    for file in opt
+    let mut files = opt
         .input
-        .map_or_else(|| vec![None], |f| f.into_iter().map(Some).collect())
-    {
-        let input = match file {
-            Some(ref path) if path.as_os_str() != "-" => {
-                let f = File::open(path)
-                    .with_context(|| format!("could not open {}", path.display()))?;
-                let size = f.metadata().ok().map(|m| m.len());
-                if size.is_none() {
-                    warn!("could not query metadata about input file");
+        .clone()
+        .map_or_else(|| vec![None], |f| f.into_iter().map(Some).collect());
+    if let Some(ref path) = opt.files_from {
+        if opt.input.is_none() {
+            files.clear();
+        }
+        files.extend(read_file_list(path, opt.null)?.into_iter().map(Some));
+    }
+    if opt.recursive {
+        files = files
+            .into_iter()
+            .map(|file| match file {
+                Some(ref dir) if dir.is_dir() => walk::files(dir)
+                    .map(|files| files.into_iter().map(Some).collect())
+                    .map_err(|err| Error::io(format!("could not read {}", dir.display()), err)),
+                file => Ok(vec![file]),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+    let mut filtered = Vec::new();
+    files.retain(|file| match file {
+        Some(path) if path.as_os_str() != "-" => excluded_by(&opt, path)
+            .map(|filter| filtered.push(filter))
+            .is_none(),
+        _ => true,
+    });
+    report_filtered(&opt, &filtered);
+    if opt.output.is_some() && files.len() > 1 {
+        return Err(Error::Usage(
+            "the argument '--output <FILE>' cannot be used with multiple input files".into(),
+        ));
+    }
+
+    let chunk_size = opt
+        .memory_limit
+        .map_or(encoder::DEFAULT_CHUNK_SIZE, |limit| {
+            // The limit is shared by all threads compressing files in parallel.
+            let workers = if opt.stdout || opt.watch.is_some() {
+                1
+            } else if opt.static_root.is_some() {
+                jobs
+            } else {
+                jobs.min(files.len()).max(1)
+            };
+            let limit = limit.as_u64() / workers as u64;
+            if limit < encoder::MIN_MEMORY_LIMIT {
+                warn!(
+                    "the memory limit is too low, at least {:#.2} per job will be used",
+                    Byte::from(encoder::MIN_MEMORY_LIMIT).get_appropriate_unit(UnitType::Binary)
+                );
+            }
+            encoder::chunk_size(limit)
+        });
+    let settings = Settings {
+        opt: &opt,
+        options,
+        format,
+        extension,
+        chunk_size,
+        jobserver,
+        overwrite: opt.overwrite.or_else(|| {
+            (opt.force || opt.update || opt.watch.is_some() || opt.static_root.is_some())
+                .then_some(Overwrite::Always)
+        }),
+        cache: opt
+            .cache_dir
+            .clone()
+            .map(|dir| Cache::new(dir, opt.cache_size.as_u64())),
+        budgets: load_budgets(&opt)?,
+    };
+    if let Some(ref dir) = opt.watch {
+        return watch_dir(&settings, dir);
+    }
+    // The manifest is loaded first, so that an invalid manifest is reported
+    // before compressing files.
+    let mut manifest = load_manifest(&opt)?;
+    if let Some(ref root) = opt.static_root {
+        return precompress(&settings, root, jobs, manifest.as_mut());
+    }
+    let total = files.len();
+    let recorded = match opt.checksum_file {
+        Some(ref path) if opt.update && opt.checksum_input => recorded_checksums(path)?,
+        _ => HashMap::new(),
+    };
+    let up_to_date = files
+        .iter()
+        .map(|file| match file {
+            Some(path) if opt.update && path.as_os_str() != "-" => {
+                up_to_date(&settings, path, &recorded)
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let pending = files
+        .iter()
+        .zip(&up_to_date)
+        .filter(|(_, outcome)| outcome.is_none())
+        .map(|(file, _)| file.clone())
+        .collect::<Vec<_>>();
+    // Compressed data written to standard output must not be interleaved.
+    let jobs = if opt.stdout {
+        1
+    } else {
+        jobs.min(pending.len())
+    };
+    // Errors are reported here if there are multiple files, even if only one
+    // of them is compressed, since the error is not returned.
+    let mut results = compress_files(&settings, &pending, jobs, total > 1).into_iter();
+    let (mut compressed, mut skipped, mut failed) = (0, 0, 0);
+    let mut checksums = String::new();
+    let mut violations = Vec::new();
+    for outcome in up_to_date {
+        let result = match outcome {
+            Some(outcome) => {
+                skipped += 1;
+                Ok(outcome)
+            }
+            None => match results.next().flatten() {
+                // The output file already exists and is not overwritten.
+                Some(Ok(outcome)) if outcome.skipped => {
+                    skipped += 1;
+                    continue;
+                }
+                Some(Ok(outcome)) => {
+                    compressed += 1;
+                    Ok(outcome)
+                }
+                Some(Err(err)) => Err(err),
+                None => continue,
+            },
+        };
+        match result {
+            Ok(outcome) => {
+                if let Some(ref mut manifest) = manifest {
+                    add_to_manifest(manifest, &settings, &outcome);
+                }
+                check_budgets(&settings, &outcome, &mut violations);
+                if opt.checksum_file.is_some() {
+                    if opt.checksum_input {
+                        checksums.push_str(&hash::checksum_line(
+                            &outcome.input_digest,
+                            outcome.input.as_deref(),
+                        ));
+                    }
+                    checksums.push_str(&hash::checksum_line(
+                        &outcome.output_digest,
+                        outcome.output.as_deref(),
+                    ));
                 }
-                (Input::File(f), Some(path), size)
             }
-            _ => {
-                let stdin = io::stdin();
-                if stdin.is_terminal() && !opt.force {
-                    bail!("standard input is a terminal");
+            Err(err) if total == 1 || matches!(err, Error::BrokenPipe) => return Err(err),
+            Err(_) => failed += 1,
+        }
+    }
+    evict_cache(&settings);
+    if total > 1 {
+        info!(
+            stage = "summary",
+            compressed,
+            skipped,
+            failed;
+            "{compressed} files have been {}, {skipped} files have been skipped, {failed} files could not be processed",
+            if opt.decompress { "decompressed" } else { "compressed" }
+        );
+    }
+
+    if let Some(ref mut manifest) = manifest {
+        save_manifest(manifest)?;
+    }
+    if let Some(ref path) = opt.checksum_file {
+        fs::write(path, checksums).map_err(|err| {
+            Error::io(
+                format!("could not write checksums to {}", path.display()),
+                err,
+            )
+        })?;
+    }
+    finish(failed, total, violations)
+}
+
+/// Prints the statistics of the cache or clears it.
+fn run_cache(opt: &Opt) -> Result<(), Error> {
+    let dir = opt
+        .cache_dir
+        .as_ref()
+        .expect("`--cache-dir` should be required");
+    let cache = Cache::new(dir.clone(), opt.cache_size.as_u64());
+    let context = || format!("could not read the cache in {}", dir.display());
+    if opt.cache_stats {
+        let stats = cache.stats().map_err(|err| Error::io(context(), err))?;
+        println!("Directory: {}", dir.display());
+        println!("Entries: {}", stats.entries);
+        println!(
+            "Size: {:#.2} / {:#.2}",
+            Byte::from(stats.size).get_appropriate_unit(UnitType::Binary),
+            opt.cache_size.get_appropriate_unit(UnitType::Binary)
+        );
+    } else {
+        let removed = cache.clear().map_err(|err| Error::io(context(), err))?;
+        info!(stage = "cache", removed; "{removed} entries have been removed from the cache");
+    }
+    Ok(())
+}
+
+/// Removes entries which are used least recently from the cache until its
+/// size does not exceed the limit.
+fn evict_cache(settings: &Settings<'_>) {
+    if let Some(ref cache) = settings.cache {
+        match cache.evict() {
+            Ok(0) => {}
+            Ok(evicted) => {
+                info!(stage = "cache", evicted; "{evicted} entries have been evicted from the cache");
+            }
+            Err(err) => warn!("could not evict entries from the cache: {err}"),
+        }
+    }
+}
+
+/// Returns the digests of input files recorded in the checksum file at `path`.
+///
+/// If the checksum file does not exist, the result is empty.
+fn recorded_checksums(path: &Path) -> Result<HashMap<String, Vec<u8>>, Error> {
+    let checksums = match fs::read_to_string(path) {
+        Ok(checksums) => checksums,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(err) => {
+            return Err(Error::io(
+                format!("could not read checksums from {}", path.display()),
+                err,
+            ));
+        }
+    };
+    Ok(checksums
+        .lines()
+        .filter_map(hash::parse_checksum_line)
+        .map(|(digest, name)| (name, digest))
+        .collect())
+}
+
+/// A filter which excludes input files.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Filter {
+    Include,
+    Exclude,
+    MinSize,
+    MaxSize,
+}
+
+/// Returns the filter which excludes the input file at `path`, or [`None`] if
+/// it is not excluded.
+fn excluded_by(opt: &Opt, path: &Path) -> Option<Filter> {
+    if !opt.include.is_empty() && !opt.include.iter().any(|glob| glob.matches(path)) {
+        return Some(Filter::Include);
+    }
+    if opt.exclude.iter().any(|glob| glob.matches(path)) {
+        return Some(Filter::Exclude);
+    }
+    if opt.min_size.is_none() && opt.max_size.is_none() {
+        return None;
+    }
+    // Files whose size is unknown are not excluded, so that the error is
+    // reported when they are processed.
+    let size = fs::metadata(path).ok()?.len();
+    if opt.min_size.is_some_and(|min| size < min.as_u64()) {
+        return Some(Filter::MinSize);
+    }
+    if opt.max_size.is_some_and(|max| size > max.as_u64()) {
+        return Some(Filter::MaxSize);
+    }
+    None
+}
+
+/// Logs the number of files excluded by each filter which is specified.
+fn report_filtered(opt: &Opt, filtered: &[Filter]) {
+    for (filter, name, is_specified) in [
+        (Filter::Include, "--include", !opt.include.is_empty()),
+        (Filter::Exclude, "--exclude", !opt.exclude.is_empty()),
+        (Filter::MinSize, "--min-size", opt.min_size.is_some()),
+        (Filter::MaxSize, "--max-size", opt.max_size.is_some()),
+    ] {
+        if is_specified {
+            let count = filtered.iter().filter(|&&f| f == filter).count();
+            info!(
+                stage = "filter",
+                filter = name,
+                count;
+                "{count} files have been excluded by {name}"
+            );
+        }
+    }
+}
+
+/// Loads the manifest if `--manifest` is specified.
+fn load_manifest(opt: &Opt) -> Result<Option<Manifest>, Error> {
+    opt.manifest
+        .as_deref()
+        .map(|path| {
+            Manifest::load(path).map_err(|err| {
+                let context = format!("could not read the manifest from {}", path.display());
+                if err.kind() == io::ErrorKind::InvalidData {
+                    Error::data(context, err)
+                } else {
+                    Error::io(context, err)
+                }
+            })
+        })
+        .transpose()
+}
+
+/// Adds the entry for the file of `outcome` to `manifest`.
+///
+/// Files which are not compressed, such as those whose output file is not
+/// overwritten, are not added.
+fn add_to_manifest(manifest: &mut Manifest, settings: &Settings<'_>, outcome: &Outcome) {
+    let (Some(input), Some(output), Some(original_size), Some(compressed_size)) = (
+        &outcome.input,
+        &outcome.output,
+        outcome.input_size,
+        outcome.output_size,
+    ) else {
+        return;
+    };
+    // The digest of the output file is reused if it is the `ETag`, so that the
+    // file is not read again.
+    let digest = (settings.opt.checksum_algorithm == HashAlgorithm::Sha256
+        && !outcome.output_digest.is_empty())
+    .then_some(outcome.output_digest.as_slice());
+    if let Err(err) = manifest.insert(
+        input,
+        output,
+        original_size,
+        compressed_size,
+        settings.format,
+        digest,
+    ) {
+        warn!(
+            file:% = input.display(),
+            stage = "manifest",
+            output:% = output.display();
+            "could not add {} to the manifest: {err}",
+            output.display()
+        );
+    }
+}
+
+/// Returns the error for the end of the run, if any.
+///
+/// Files which could not be processed take precedence over budgets, since their
+/// output files are missing.
+fn finish(failed: usize, total: usize, violations: Vec<Violation>) -> Result<(), Error> {
+    if failed > 0 {
+        if !violations.is_empty() {
+            warn!(
+                "{} files exceeded their size budgets",
+                budget::count_files(&violations)
+            );
+        }
+        return Err(Error::PartialFailure { failed, total });
+    }
+    if !violations.is_empty() {
+        return Err(Error::BudgetExceeded(violations));
+    }
+    Ok(())
+}
+
+/// Loads the budgets specified by `--budget` and `--budget-file`.
+fn load_budgets(opt: &Opt) -> Result<Vec<Budget>, Error> {
+    let mut budgets = opt.budget.clone();
+    if let Some(ref path) = opt.budget_file {
+        let loaded = budget::load(path).map_err(|err| {
+            let context = format!("could not read the budgets from {}", path.display());
+            if err.kind() == io::ErrorKind::InvalidData {
+                Error::data(context, err)
+            } else {
+                Error::io(context, err)
+            }
+        })?;
+        budgets.extend(loaded);
+    }
+    Ok(budgets)
+}
+
+/// Checks the output file of `outcome` against the budgets, and adds the
+/// budgets which it exceeds to `violations`.
+fn check_budgets(settings: &Settings<'_>, outcome: &Outcome, violations: &mut Vec<Violation>) {
+    let (Some(output), Some(original_size), Some(compressed_size)) =
+        (&outcome.output, outcome.input_size, outcome.output_size)
+    else {
+        return;
+    };
+    violations.extend(
+        settings
+            .budgets
+            .iter()
+            .filter_map(|budget| budget.check(output, original_size, compressed_size)),
+    );
+}
+
+/// Writes `manifest`.
+fn save_manifest(manifest: &mut Manifest) -> Result<(), Error> {
+    manifest.save().map_err(|err| {
+        Error::io(
+            format!(
+                "could not write the manifest to {}",
+                manifest.path().display()
+            ),
+            err,
+        )
+    })
+}
+
+/// Reads the list of input files from `path`, or standard input if `path` is
+/// "-".
+fn read_file_list(path: &Path, null: bool) -> Result<Vec<PathBuf>, Error> {
+    let result = if path.as_os_str() == "-" {
+        input::read_file_list(io::stdin().lock(), null)
+    } else {
+        let f = File::open(path).map_err(|source| Error::InputNotFound {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        input::read_file_list(f, null)
+    };
+    result.map_err(|err| Error::io(format!("could not read {}", path.display()), err))
+}
+
+/// Returns the outcome of `input` without compressing it if its output file is
+/// up to date, or [`None`] otherwise.
+///
+/// The output file is up to date if it is a regular file which is not older
+/// than `input`, or if the digest of `input` matches the one in `recorded`. The
+/// digests in the outcome are only computed if the checksum file is written.
+fn up_to_date(
+    settings: &Settings<'_>,
+    input: &Path,
+    recorded: &HashMap<String, Vec<u8>>,
+) -> Option<Outcome> {
+    let opt = settings.opt;
+    let output = match opt.output {
+        Some(ref path) => path.clone(),
+        None => output_path(
+            input,
+            settings.extension,
+            opt.output_dir.as_deref(),
+            opt.mirror,
+        )
+        .ok()?,
+    };
+    // A directory or other non-regular file at the output path is not an
+    // output file, so compressing the input reports it as an error.
+    let output_metadata = fs::metadata(&output).ok().filter(fs::Metadata::is_file)?;
+    let output_modified = output_metadata.modified().ok()?;
+    let input_metadata = fs::metadata(input).ok();
+    let is_newer = input_metadata
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .is_some_and(|modified| output_modified >= modified);
+    let digest = |path: &Path| -> Option<Vec<u8>> {
+        let mut hasher = Hasher::new(opt.checksum_algorithm);
+        io::copy(&mut File::open(path).ok()?, &mut hasher).ok()?;
+        Some(hasher.finalize())
+    };
+
+    let input_digest = if is_newer && !(opt.checksum_file.is_some() && opt.checksum_input) {
+        Vec::new()
+    } else {
+        let input_digest = digest(input)?;
+        // A file whose content is not changed is up to date even if it is
+        // touched, such as by checking it out again.
+        if !is_newer && recorded.get(input.to_string_lossy().as_ref()) != Some(&input_digest) {
+            return None;
+        }
+        input_digest
+    };
+    let output_digest = if opt.checksum_file.is_some() {
+        digest(&output)?
+    } else {
+        Vec::new()
+    };
+    info!(
+        file:% = input.display(),
+        stage = "skip",
+        output:% = output.display();
+        "{} is up to date",
+        output.display()
+    );
+    Some(Outcome {
+        input: Some(input.to_path_buf()),
+        output: Some(output),
+        input_digest,
+        output_digest,
+        input_size: input_metadata.map(|m| m.len()),
+        output_size: Some(output_metadata.len()),
+        skipped: true,
+    })
+}
+
+/// Precompresses files under `root` for serving static files.
+///
+/// Only files which are out of date are compressed, and output files whose
+/// source file no longer exists are removed.
+#[allow(clippy::too_many_lines)]
+fn precompress(
+    settings: &Settings<'_>,
+    root: &Path,
+    jobs: usize,
+    mut manifest: Option<&mut Manifest>,
+) -> Result<(), Error> {
+    let opt = settings.opt;
+    let is_eligible = |path: &Path| {
+        path.extension().is_some_and(|ext| {
+            opt.static_ext
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e.trim_start_matches('.')))
+        })
+    };
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let (mut removed, mut up_to_date) = (0, 0);
+    let mut files = Vec::new();
+    let mut filtered = Vec::new();
+    let mut violations = Vec::new();
+    for path in walk::files(root)
+        .map_err(|err| Error::io(format!("could not read {}", root.display()), err))?
+    {
+        if let Some(source) = source_path(&path, settings.extension) {
+            // Only output files of eligible files are regarded as stale, so
+            // that other compressed files are kept.
+            if is_eligible(&source) && fs::symlink_metadata(&source).is_err() {
+                match fs::remove_file(&path) {
+                    Ok(()) => {
+                        info!(
+                            file:% = source.display(),
+                            stage = "remove",
+                            output:% = path.display();
+                            "{} has been removed because its source does not exist",
+                            path.display()
+                        );
+                        removed += 1;
+                        if let Some(ref mut manifest) = manifest {
+                            manifest.remove(&source);
+                        }
+                    }
+                    Err(err) => warn!(
+                        file:% = source.display(),
+                        stage = "remove",
+                        output:% = path.display();
+                        "could not remove {}: {err}",
+                        path.display()
+                    ),
                 }
-                (Input::Stdin(stdin), None, None)
             }
+            continue;
+        }
+        if !is_eligible(&path) {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(&path) else {
+            warn!(
+                file:% = path.display(),
+                stage = "static";
+                "could not query metadata about {}",
+                path.display()
+            );
+            continue;
         };
+        if let Some(filter) = excluded_by(opt, &path) {
+            filtered.push(filter);
+            continue;
+        }
+        let output = output_path(&path, settings.extension, None, false)?;
+        if let (Ok(input), Some(output_modified)) = (metadata.modified(), modified(&output)) {
+            if output_modified >= input {
+                let outcome = Outcome {
+                    input_size: Some(metadata.len()),
+                    output_size: fs::metadata(&output).ok().map(|m| m.len()),
+                    ..Outcome::skipped(Some(&path), Some(output))
+                };
+                // The manifest may not have the entry if it is written for the
+                // first time.
+                if let Some(ref mut manifest) = manifest {
+                    add_to_manifest(manifest, settings, &outcome);
+                }
+                // Output files which are up to date are also checked, so that
+                // the run fails until they fit in the budgets.
+                check_budgets(settings, &outcome, &mut violations);
+                up_to_date += 1;
+                continue;
+            }
+        }
+        files.push(Some(path));
+    }
+    report_filtered(opt, &filtered);
 
-        let mut output = match input.1 {
-            Some(path) if !opt.stdout => {
-                let mut output_path = path.clone();
-                output_path.as_mut_os_string().push(extension);
-                let f = if opt.force {
-                    File::create(&output_path)
-                } else {
-                    File::create_new(&output_path)
+    let total = files.len();
+    // Files excluded by the filters and those whose output file is up to date
+    // are also skipped.
+    let (mut compressed, mut skipped, mut failed) = (0, filtered.len() + up_to_date, 0);
+    for (file, result) in
+        files
+            .iter()
+            .zip(compress_files(settings, &files, jobs.min(total), total > 1))
+    {
+        match result {
+            // The input file is already compressed or is not a regular file,
+            // or the output file is not overwritten.
+            Some(Ok(outcome)) if outcome.skipped => skipped += 1,
+            Some(Ok(outcome)) => {
+                compressed += 1;
+                if let Some(ref mut manifest) = manifest {
+                    add_to_manifest(manifest, settings, &outcome);
+                }
+                check_budgets(settings, &outcome, &mut violations);
+                let (Some(input), Some(output)) = (file, outcome.output) else {
+                    continue;
+                };
+                // Some servers regard an output file as stale unless its
+                // modification time is the same as that of the source file.
+                let result = modified(input)
+                    .ok_or_else(|| io::ErrorKind::NotFound.into())
+                    .and_then(|mtime| {
+                        File::options()
+                            .write(true)
+                            .open(&output)
+                            .and_then(|f| f.set_modified(mtime))
+                    });
+                if let Err(err) = result {
+                    warn!(
+                        file:% = input.display(),
+                        stage = "static",
+                        output:% = output.display();
+                        "could not set the modification time of {}: {err}",
+                        output.display()
+                    );
                 }
-                .with_context(|| format!("could not open {}", output_path.display()))?;
-                (Output::File(f), Some(output_path), None)
             }
-            _ => {
-                let stdout = io::stdout();
-                if stdout.is_terminal() && !(opt.stdout || opt.force) {
-                    bail!("compressed data not written to a terminal");
+            Some(Err(err)) if total == 1 => return Err(err),
+            Some(Err(_)) => failed += 1,
+            None => {}
+        }
+    }
+    evict_cache(settings);
+    info!(
+        stage = "static",
+        compressed,
+        skipped,
+        removed;
+        "{compressed} files have been compressed, {skipped} files have been skipped, {removed} stale files have been removed"
+    );
+    if let Some(manifest) = manifest {
+        save_manifest(manifest)?;
+    }
+    finish(failed, total, violations)
+}
+
+/// Returns the path of the source file of the output file at `path`, or
+/// [`None`] if `path` does not have `extension`.
+fn source_path(path: &Path, extension: &str) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?.strip_suffix(extension)?;
+    (!extension.is_empty() && !name.is_empty()).then(|| path.with_file_name(name))
+}
+
+/// An event which is sent to the thread dispatching files.
+#[derive(Debug)]
+enum Event {
+    /// A file has been processed. The token which was used is also returned.
+    Done(usize, Result<Outcome, Error>, Option<u8>),
+
+    /// A token has been acquired from the jobserver.
+    Token(io::Result<u8>),
+}
+
+/// Compresses `files` using up to `jobs` threads.
+///
+/// The results are in the same order as `files`. If `report_errors` is `true`,
+/// errors are reported as soon as they occur. If standard output is closed,
+/// the remaining files are skipped and their results are [`None`].
+///
+/// Files whose content is identical to that of a preceding file are not
+/// compressed again. Instead, they are processed after the preceding file, and
+/// its compressed data is copied to their output files.
+fn compress_files(
+    settings: &Settings<'_>,
+    files: &[Option<PathBuf>],
+    jobs: usize,
+    report_errors: bool,
+) -> Vec<Option<Result<Outcome, Error>>> {
+    let sources = identical_sources(settings, files);
+    let (originals, duplicates): (Vec<_>, Vec<_>) =
+        (0..files.len()).partition(|&i| sources[i].is_none());
+
+    let mut results = iter::repeat_with(|| None)
+        .take(files.len())
+        .collect::<Vec<_>>();
+    let tasks = originals
+        .iter()
+        .map(|&i| (files[i].as_deref(), None))
+        .collect::<Vec<_>>();
+    for (&i, result) in originals.iter().zip(run_tasks(
+        settings,
+        &tasks,
+        jobs.min(tasks.len()),
+        report_errors,
+    )) {
+        results[i] = result;
+    }
+    if duplicates.is_empty() {
+        return results;
+    }
+    let tasks = duplicates
+        .iter()
+        .map(|&i| {
+            // If the original file was not compressed, the duplicate is
+            // compressed by itself.
+            let original = sources[i].and_then(|j| match results[j] {
+                Some(Ok(ref outcome)) if !outcome.skipped && outcome.output.is_some() => {
+                    Some(outcome)
                 }
-                (Output::Stdout(stdout), None, None)
+                _ => None,
+            });
+            (files[i].as_deref(), original)
+        })
+        .collect::<Vec<_>>();
+    let duplicate_results = run_tasks(settings, &tasks, jobs.min(tasks.len()), report_errors);
+    drop(tasks);
+    for (i, result) in duplicates.into_iter().zip(duplicate_results) {
+        results[i] = result;
+    }
+    results
+}
+
+/// Returns the index of the preceding file whose content is identical to that
+/// of each of `files`, or [`None`] if there is no such file.
+///
+/// Hard links to the same file are regarded as identical without reading them
+/// again. Only regular files are compared, since opening a FIFO blocks.
+fn identical_sources(settings: &Settings<'_>, files: &[Option<PathBuf>]) -> Vec<Option<usize>> {
+    let opt = settings.opt;
+    let mut sources = vec![None; files.len()];
+    if files.len() < 2 || opt.decompress || opt.stdout || opt.output.is_some() {
+        return sources;
+    }
+    let mut by_id = HashMap::new();
+    let mut by_digest = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        let Some(path) = file.as_deref().filter(|p| p.as_os_str() != "-") else {
+            continue;
+        };
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let id = input::file_id(&metadata);
+        if let Some(&j) = id.as_ref().and_then(|id| by_id.get(id)) {
+            sources[i] = Some(j);
+            continue;
+        }
+        let digest = File::open(path).and_then(|mut f| {
+            let mut hasher = Hasher::new(HashAlgorithm::Blake3);
+            io::copy(&mut f, &mut hasher)?;
+            Ok(hasher.finalize())
+        });
+        let Ok(digest) = digest else {
+            continue;
+        };
+        let original = *by_digest.entry(digest).or_insert(i);
+        if original != i {
+            sources[i] = Some(original);
+        }
+        if let Some(id) = id {
+            by_id.insert(id, original);
+        }
+    }
+    sources
+}
+
+/// Runs `tasks` using up to `jobs` threads, and returns the results in the
+/// same order.
+///
+/// Each task is a file to compress, and the outcome of a file whose content is
+/// identical if any.
+#[allow(clippy::too_many_lines)]
+fn run_tasks(
+    settings: &Settings<'_>,
+    tasks: &[(Option<&Path>, Option<&Outcome>)],
+    jobs: usize,
+    report_errors: bool,
+) -> Vec<Option<Result<Outcome, Error>>> {
+    let total = tasks.len();
+    let jobserver = settings.jobserver.as_ref().filter(|_| jobs > 1);
+    let (sender, receiver) = mpsc::channel();
+    // Acquiring a token blocks, so it is done by another thread. The thread
+    // stops waiting for a token once all files are processed, and it is joined
+    // before returning, so that no token is lost when the program exits.
+    let finished = Arc::new(Mutex::new(false));
+    let (mut requests, token_thread) = jobserver
+        .map(|client| {
+            let (requests, pending) = mpsc::channel::<()>();
+            let (client, sender, finished) =
+                (Arc::clone(client), sender.clone(), Arc::clone(&finished));
+            let token_thread = thread::spawn(move || {
+                for () in pending {
+                    let token =
+                        client.acquire(|| *finished.lock().unwrap_or_else(PoisonError::into_inner));
+                    // The lock is held while sending, so that the token is
+                    // either received or released.
+                    let is_finished = finished.lock().unwrap_or_else(PoisonError::into_inner);
+                    let Some(token) = token.transpose() else {
+                        break;
+                    };
+                    if *is_finished {
+                        if let Ok(token) = token {
+                            let _ = client.release(token);
+                        }
+                        break;
+                    }
+                    let _ = sender.send(Event::Token(token));
+                    drop(is_finished);
+                }
+            });
+            (requests, token_thread)
+        })
+        .unzip();
+    let release = |token| {
+        if let (Some(client), Some(token)) = (jobserver, token) {
+            if let Err(err) = client.release(token) {
+                warn!("could not release a token to the jobserver: {err}");
             }
+        }
+    };
+
+    let mut results = iter::repeat_with(|| None).take(total).collect::<Vec<_>>();
+    thread::scope(|s| {
+        let start = |i: usize, token| {
+            let sender = sender.clone();
+            s.spawn(move || {
+                let (file, original) = tasks[i];
+                let result = compress_file(settings, file, original);
+                let _ = sender.send(Event::Done(i, result, token));
+            });
         };
-        if let Some(ref path) = output.1 {
-            if !opt.stdout {
-                info!("Saving to: {}", path.display());
+        let (mut next, mut running) = (0, 0);
+        // Every process has an implicit token, which is not acquired from the
+        // jobserver. Without the jobserver, the number of threads is only
+        // limited by `jobs`.
+        let mut has_implicit_token = true;
+        let mut requested = false;
+        loop {
+            while next < total && running < jobs && (jobserver.is_none() || has_implicit_token) {
+                start(next, None);
+                has_implicit_token = false;
+                (next, running) = (next + 1, running + 1);
+            }
+            if let Some(ref requests) = requests {
+                if next < total && running < jobs && !requested {
+                    requested = requests.send(()).is_ok();
+                }
+            }
+            if running == 0 {
+                break;
+            }
+            match receiver.recv().expect("the sender should not be dropped") {
+                Event::Done(i, result, token) => {
+                    running -= 1;
+                    if token.is_some() {
+                        release(token);
+                    } else {
+                        has_implicit_token = true;
+                    }
+                    match result {
+                        Err(Error::BrokenPipe) => next = total,
+                        Err(ref err) if report_errors => {
+                            let stage = if settings.opt.decompress {
+                                "decompress"
+                            } else {
+                                "compress"
+                            };
+                            err.log(tasks[i].0, stage);
+                        }
+                        _ => {}
+                    }
+                    results[i] = Some(result);
+                }
+                Event::Token(Ok(token)) => {
+                    requested = false;
+                    if next < total && running < jobs {
+                        start(next, Some(token));
+                        (next, running) = (next + 1, running + 1);
+                    } else {
+                        release(Some(token));
+                    }
+                }
+                Event::Token(Err(err)) => {
+                    warn!("could not acquire a token from the jobserver: {err}");
+                    requested = false;
+                    requests = None;
+                }
             }
         }
+    });
+    *finished.lock().unwrap_or_else(PoisonError::into_inner) = true;
+    drop(requests);
+    if let Some(token_thread) = token_thread {
+        let _ = token_thread.join();
+    }
+    for event in receiver.try_iter() {
+        if let Event::Token(Ok(token)) = event {
+            release(Some(token));
+        }
+    }
+    results
+}
+
+/// Compresses files in `dir` whenever they are written, until the program is
+/// terminated.
+fn watch_dir(settings: &Settings<'_>, dir: &Path) -> Result<(), Error> {
+    let opt = settings.opt;
+    let output_of = |path: &Path| {
+        output_path(
+            path,
+            settings.extension,
+            opt.output_dir.as_deref(),
+            opt.mirror,
+        )
+    };
+    // Output files may be written in the watched directory.
+    let is_output = |path: &Path| {
+        !settings.extension.is_empty()
+            && path
+                .as_os_str()
+                .as_encoded_bytes()
+                .ends_with(settings.extension.as_bytes())
+    };
+    info!("Watching {}", dir.display());
+    watch::watch(dir, |change| {
+        let (Change::Found(ref file) | Change::Written(ref file) | Change::Removed(ref file)) =
+            change;
+        let file = file.clone();
+        let result = match change {
+            Change::Found(ref path) | Change::Written(ref path)
+                if is_output(path) || excluded_by(opt, path).is_some() =>
+            {
+                Ok(())
+            }
+            Change::Found(path) => output_of(&path).and_then(|output| {
+                let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+                match (modified(&path), modified(&output)) {
+                    (Some(input), Some(output)) if output >= input => Ok(()),
+                    _ => compress_file(settings, Some(&path), None).map(|_| ()),
+                }
+            }),
+            Change::Written(path) => compress_file(settings, Some(&path), None).map(|_| ()),
+            Change::Removed(ref path) if is_output(path) => Ok(()),
+            Change::Removed(path) => {
+                output_of(&path).and_then(|output| match fs::remove_file(&output) {
+                    Ok(()) => {
+                        info!(
+                            file:% = path.display(),
+                            stage = "remove",
+                            output:% = output.display();
+                            "{} has been removed",
+                            output.display()
+                        );
+                        Ok(())
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                    Err(err) => Err(Error::io(
+                        format!("could not remove {}", output.display()),
+                        err,
+                    )),
+                })
+            }
+        };
+        if let Err(err) = result {
+            err.log(Some(&file), "watch");
+        }
+        evict_cache(settings);
+    })
+    .map_err(|err| Error::io(format!("could not watch {}", dir.display()), err))
+}
 
-        zopfli::compress(zopfli_opt, format, BufReader::new(input.0), &mut output.0)
-            .context("data could not be compressed")?;
+/// Compresses `file`, or standard input if `file` is [`None`] or "-".
+///
+/// If `original` is specified, which is the outcome of a file whose content is
+/// identical, its output file is copied instead of compressing `file`. If
+/// `--decompress` is specified, `file` is decompressed instead.
+#[allow(clippy::too_many_lines)]
+fn compress_file(
+    settings: &Settings<'_>,
+    file: Option<&Path>,
+    original: Option<&Outcome>,
+) -> Result<Outcome, Error> {
+    let opt = settings.opt;
 
-        if let Output::File(f) = output.0 {
+    let mut input = match file {
+        Some(path) if path.as_os_str() != "-" => {
+            // This is checked before opening the file, since opening a FIFO
+            // blocks until it is opened for writing.
+            if let Some(reason) = unsuitable_input(opt, path) {
+                warn!(
+                    file:% = path.display(),
+                    stage = "skip";
+                    "{} {reason}, so it has been skipped",
+                    path.display()
+                );
+                return Ok(Outcome::skipped(Some(path), None));
+            }
+            let f = File::open(path).map_err(|source| Error::InputNotFound {
+                path: path.to_path_buf(),
+                source,
+            })?;
             let size = f.metadata().ok().map(|m| m.len());
             if size.is_none() {
-                warn!("could not query metadata about output file");
+                warn!(
+                    file:% = path.display(),
+                    stage = "open";
+                    "could not query metadata about input file"
+                );
+            }
+            (Input::File(f), Some(path), size)
+        }
+        _ => {
+            let stdin = io::stdin();
+            if stdin.is_terminal() && !opt.force {
+                return Err(Error::Usage("standard input is a terminal".into()));
+            }
+            (Input::Stdin(stdin), None, None)
+        }
+    };
+
+    if let (Input::File(f), Some(path), false) =
+        (&mut input.0, input.1, opt.force || opt.decompress)
+    {
+        let reason = already_compressed(settings.extension, path, f)
+            .map_err(|err| Error::io(format!("could not read {}", path.display()), err))?;
+        if let Some(reason) = reason {
+            warn!(
+                file:% = path.display(),
+                stage = "skip";
+                "{} {reason}, so it has been skipped",
+                path.display()
+            );
+            return Ok(Outcome::skipped(Some(path), None));
+        }
+    }
+
+    let output_path = match (&opt.output, input.1) {
+        (Some(path), _) => Some(path.clone()),
+        (None, Some(path)) if !opt.stdout && opt.decompress => Some(decompressed_path(
+            path,
+            settings.extension,
+            opt.output_dir.as_deref(),
+            opt.mirror,
+        )?),
+        (None, Some(path)) if !opt.stdout => Some(output_path(
+            path,
+            settings.extension,
+            opt.output_dir.as_deref(),
+            opt.mirror,
+        )?),
+        _ => None,
+    };
+    let mut output = if let Some(output_path) = output_path {
+        if opt.output_dir.is_some() {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent).map_err(|err| {
+                    Error::io(format!("could not create {}", parent.display()), err)
+                })?;
             }
-            output.2 = size;
         }
-        if let (Some(is), Some(os)) = (input.2, output.2) {
-            #[allow(clippy::cast_precision_loss)]
-            let space_saving = (1.0 - (os as f64 / is as f64)) * 100.0;
+        let Some((f, output_path)) = create_output(settings, &output_path)? else {
+            let name = input
+                .1
+                .map_or_else(|| "-".into(), |p| p.display().to_string());
+            warn!(
+                file = name.as_str(),
+                stage = "skip",
+                output:% = output_path.display();
+                "{} already exists and is not overwritten, so {name} has been skipped",
+                output_path.display()
+            );
+            return Ok(Outcome::skipped(input.1, Some(output_path)));
+        };
+        (Output::File(f), Some(output_path), None)
+    } else {
+        let stdout = io::stdout();
+        if stdout.is_terminal() && !(opt.stdout || opt.force || opt.decompress) {
+            return Err(Error::Usage(
+                "compressed data not written to a terminal".into(),
+            ));
+        }
+        (Output::Stdout(stdout), None, None)
+    };
+    let partial = output.1.as_deref().map(PartialOutput::new);
+    let name = input
+        .1
+        .map_or_else(|| "-".into(), |p| p.display().to_string());
+    if let Some(ref path) = output.1 {
+        if !opt.stdout {
             info!(
-                "Original Size: {:#.2}, Compressed: {:#.2}, Compression: {:.2}% Removed",
-                Byte::from(is).get_appropriate_unit(UnitType::Binary),
-                Byte::from(os).get_appropriate_unit(UnitType::Binary),
-                space_saving
+                file = name.as_str(),
+                stage = "open",
+                output:% = path.display();
+                "Saving to: {}",
+                path.display()
             );
         }
+    }
 
-        if opt.remove {
-            if let Some(path) = input.1 {
+    let start = Instant::now();
+    let stage = if opt.decompress {
+        "decompress"
+    } else {
+        "compress"
+    };
+    // Standard input cannot be read twice, so it is not cached.
+    // The compressed data of the identical file is used instead of the cache.
+    // It cannot be used if the output file is the same.
+    let duplicate = original
+        .filter(|o| o.output != output.1)
+        .and_then(|o| Some((o.input.as_deref()?, File::open(o.output.as_deref()?).ok()?)));
+    let key = match (&settings.cache, &mut input.0) {
+        (Some(_), Input::File(f)) if !opt.decompress && duplicate.is_none() => Some(
+            Cache::key(
+                &settings.options,
+                settings.format,
+                settings.chunk_size,
+                &mut *f,
+            )
+            .and_then(|key| f.rewind().map(|()| key))
+            .map_err(|err| Error::io(format!("could not read {name}"), err))?,
+        ),
+        _ => None,
+    };
+    let cached = key
+        .as_deref()
+        .and_then(|key| settings.cache.as_ref()?.get(key));
+    let mut reader = HashReader::new(input.0, opt.checksum_algorithm);
+    let mut writer = HashWriter::new(
+        Throttle::new(&mut output.0, opt.cpu_limit),
+        opt.checksum_algorithm,
+    );
+    let is_cached = cached.is_some();
+    if opt.decompress {
+        decoder::decompress(settings.format, &mut reader, &mut writer).map(|_| ())
+    } else if let Some((source, mut entry)) = duplicate {
+        info!(
+            file = name.as_str(),
+            stage = "dedup",
+            source:% = source.display();
+            "Reusing the compressed data of {}, which is identical",
+            source.display()
+        );
+        io::copy(&mut reader, &mut io::sink())
+            .and_then(|_| io::copy(&mut entry, &mut writer))
+            .map(|_| ())
+    } else if let Some(mut entry) = cached {
+        info!(file = name.as_str(), stage = "cache"; "Reusing the cached compressed data");
+        // The input is still read to compute its digest.
+        io::copy(&mut reader, &mut io::sink())
+            .and_then(|_| io::copy(&mut entry, &mut writer))
+            .map(|_| ())
+    } else {
+        encoder::compress(
+            settings.options,
+            settings.format,
+            settings.chunk_size,
+            &mut reader,
+            &mut writer,
+        )
+    }
+    .map_err(|err| {
+        // The incomplete output file is useless.
+        if let Some(ref path) = output.1 {
+            if fs::remove_file(path).is_err() {
+                warn!(file = name.as_str(), stage; "could not remove {}", path.display());
+            }
+        }
+        if err.kind() == io::ErrorKind::BrokenPipe && output.1.is_none() {
+            Error::BrokenPipe
+        } else if opt.decompress
+            && matches!(
+                err.kind(),
+                io::ErrorKind::InvalidData
+                    | io::ErrorKind::InvalidInput
+                    | io::ErrorKind::UnexpectedEof
+            )
+        {
+            Error::data(format!("{name} could not be decompressed"), err)
+        } else if opt.decompress {
+            Error::io("data could not be decompressed", err)
+        } else {
+            Error::io("data could not be compressed", err)
+        }
+    })?;
+    let duration = start.elapsed();
+    let input_digest = reader.finalize();
+    let output_digest = writer.finalize();
+
+    if opt.verify {
+        if let Some(ref path) = output.1 {
+            if let Err(err) = verify(settings.format, path, &input_digest, opt.checksum_algorithm) {
+                if fs::remove_file(path).is_err() {
+                    warn!(file = name.as_str(), stage = "verify"; "could not remove {}", path.display());
+                }
+                return Err(err);
+            }
+            info!(file = name.as_str(), stage = "verify"; "{} has been verified", path.display());
+        } else {
+            warn!(file = name.as_str(), stage = "verify"; "could not verify data written to standard output");
+        }
+    }
+
+    if let Output::File(f) = output.0 {
+        let size = f.metadata().ok().map(|m| m.len());
+        if size.is_none() {
+            warn!(file = name.as_str(), stage; "could not query metadata about output file");
+        }
+        output.2 = size;
+        // The output file must be on disk before the input file is removed,
+        // otherwise both of them may be lost on a crash.
+        if let (Some(path), Some(_), true) = (&output.1, input.1, opt.remove) {
+            f.sync_all()
+                .and_then(|()| output::sync_parent(path))
+                .map_err(|err| {
+                    Error::io(format!("could not synchronize {}", path.display()), err)
+                })?;
+        }
+    }
+    // The output file is complete, so it is kept even if the program is
+    // terminated after this point.
+    drop(partial);
+    let duration_ms = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+    if let (Some(is), Some(os), true) = (input.2, output.2, opt.decompress) {
+        #[allow(clippy::cast_precision_loss)]
+        let space_saving = (1.0 - (is as f64 / os as f64)) * 100.0;
+        info!(
+            file = name.as_str(),
+            stage = "decompress",
+            original_size = os,
+            compressed_size = is,
+            space_saving,
+            duration_ms;
+            "Compressed: {:#.2}, Decompressed: {:#.2}, Compression: {:.2}% Removed",
+            Byte::from(is).get_appropriate_unit(UnitType::Binary),
+            Byte::from(os).get_appropriate_unit(UnitType::Binary),
+            space_saving
+        );
+    } else if let (Some(is), Some(os)) = (input.2, output.2) {
+        #[allow(clippy::cast_precision_loss)]
+        let space_saving = (1.0 - (os as f64 / is as f64)) * 100.0;
+        info!(
+            file = name.as_str(),
+            stage = "compress",
+            original_size = is,
+            compressed_size = os,
+            space_saving,
+            duration_ms;
+            "Original Size: {:#.2}, Compressed: {:#.2}, Compression: {:.2}% Removed",
+            Byte::from(is).get_appropriate_unit(UnitType::Binary),
+            Byte::from(os).get_appropriate_unit(UnitType::Binary),
+            space_saving
+        );
+    }
+
+    if let (Some(cache), Some(key), Some(path), false) =
+        (&settings.cache, key, &output.1, is_cached)
+    {
+        if let Err(err) = cache.insert(&key, path) {
+            warn!(
+                file = name.as_str(),
+                stage = "cache";
+                "could not store {} in the cache: {err}",
+                path.display()
+            );
+        }
+    }
+
+    if opt.remove {
+        if let Some(path) = input.1 {
+            // The file behind a symbolic link is removed first, so that the
+            // link is not left dangling.
+            let target = fs::symlink_metadata(path)
+                .is_ok_and(|m| m.is_symlink())
+                .then(|| fs::canonicalize(path).ok())
+                .flatten();
+            for path in target.as_deref().into_iter().chain(iter::once(path)) {
                 if fs::remove_file(path).is_ok() {
-                    info!("{} has been removed", path.display());
+                    info!(file = name.as_str(), stage = "remove"; "{} has been removed", path.display());
+                    if opt.synchronous {
+                        if let Err(err) = output::sync_parent(path) {
+                            warn!(
+                                file = name.as_str(),
+                                stage = "remove";
+                                "could not synchronize the removal of {}: {err}",
+                                path.display()
+                            );
+                        }
+                    }
                 } else {
-                    warn!("could not remove {}", path.display());
+                    warn!(file = name.as_str(), stage = "remove"; "could not remove {}", path.display());
+                }
+            }
+        }
+    }
+
+    Ok(Outcome {
+        input: input.1.map(Path::to_path_buf),
+        output: output.1,
+        input_digest,
+        output_digest,
+        input_size: input.2,
+        output_size: output.2,
+        skipped: false,
+    })
+}
+
+/// Returns why the input file at `path` is not processed, or [`None`] if it
+/// is processed.
+///
+/// Like gzip, symbolic links, files other than regular files, and files which
+/// have other hard links when they would be removed are not processed.
+fn unsuitable_input(opt: &Opt, path: &Path) -> Option<String> {
+    let mut metadata = fs::symlink_metadata(path).ok()?;
+    if metadata.is_symlink() {
+        if !opt.follow_symlinks {
+            return Some("is a symbolic link".into());
+        }
+        metadata = fs::metadata(path).ok()?;
+    }
+    if opt.force {
+        return None;
+    }
+    if metadata.is_dir() {
+        return Some("is a directory".into());
+    }
+    if !metadata.is_file() {
+        return Some("is not a regular file".into());
+    }
+    match input::link_count(&metadata) {
+        n if opt.remove && n > 1 => Some(format!("has {} other links", n - 1)),
+        _ => None,
+    }
+}
+
+/// Returns why the input file at `path` is regarded as already compressed, or
+/// [`None`] if it is not.
+///
+/// `file` is rewound after its content is checked.
+fn already_compressed(extension: &str, path: &Path, file: &mut File) -> io::Result<Option<String>> {
+    if !extension.is_empty()
+        && path
+            .file_name()
+            .is_some_and(|name| name.as_encoded_bytes().ends_with(extension.as_bytes()))
+    {
+        return Ok(Some(format!("already has {extension} suffix")));
+    }
+    let kind = Compressed::read(&mut *file)?;
+    file.rewind()?;
+    Ok(kind.map(|kind| format!("already contains {kind} data")))
+}
+
+/// Creates the output file at `path` according to the overwrite policy, and
+/// returns it with its path, which may differ from `path`.
+///
+/// Returns [`None`] if the output file already exists and is not overwritten.
+fn create_output(settings: &Settings<'_>, path: &Path) -> Result<Option<(File, PathBuf)>, Error> {
+    let open_error =
+        |path: &Path, err| Error::io(format!("could not open {}", path.display()), err);
+    let source = match File::create_new(path) {
+        Ok(f) => return Ok(Some((f, path.to_path_buf()))),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => err,
+        Err(err) => return Err(open_error(path, err)),
+    };
+    let overwrite = || File::create(path).map_err(|err| open_error(path, err));
+    match settings.overwrite {
+        None => Err(Error::OutputExists {
+            path: path.to_path_buf(),
+            source,
+        }),
+        Some(Overwrite::Never) => Ok(None),
+        Some(Overwrite::Ask) => {
+            if overwrite::ask(path).map_err(|err| Error::io("could not ask", err))? {
+                overwrite().map(|f| Some((f, path.to_path_buf())))
+            } else {
+                Ok(None)
+            }
+        }
+        Some(Overwrite::Always) => overwrite().map(|f| Some((f, path.to_path_buf()))),
+        Some(Overwrite::Backup) => {
+            let backup = overwrite::backup_path(path, settings.opt.backup)
+                .and_then(|backup| fs::rename(path, &backup).map(|()| backup))
+                .map_err(|err| Error::io(format!("could not back up {}", path.display()), err))?;
+            info!(
+                stage = "backup",
+                output:% = path.display(),
+                backup:% = backup.display();
+                "{} has been renamed to {}",
+                path.display(),
+                backup.display()
+            );
+            File::create_new(path)
+                .map(|f| Some((f, path.to_path_buf())))
+                .map_err(|err| open_error(path, err))
+        }
+        Some(Overwrite::Rename) => {
+            for n in 1.. {
+                let path = overwrite::numbered_path(path, n);
+                match File::create_new(&path) {
+                    Ok(f) => return Ok(Some((f, path))),
+                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                    Err(err) => return Err(open_error(&path, err)),
                 }
             }
+            unreachable!("a free name should be found")
         }
     }
+}
+
+/// Decompresses the file at `path` and checks that the digest of the
+/// decompressed data matches `digest`.
+fn verify(
+    format: Format,
+    path: &Path,
+    digest: &[u8],
+    algorithm: HashAlgorithm,
+) -> Result<(), Error> {
+    let context = || format!("could not verify {}", path.display());
+    let f = File::open(path).map_err(|err| Error::io(context(), err))?;
+    let mut hasher = Hasher::new(algorithm);
+    decoder::decompress(format, f, &mut hasher).map_err(|err| Error::data(context(), err))?;
+    if hasher.finalize() != digest {
+        return Err(Error::data(
+            context(),
+            "decompressed data does not match the original data",
+        ));
+    }
     Ok(())
 }
+
+/// Returns the path of the decompressed file for `input`, which is `input`
+/// without `extension`.
+///
+/// `output_dir` and `mirror` are the same as [`output_path`].
+fn decompressed_path(
+    input: &Path,
+    extension: &str,
+    output_dir: Option<&Path>,
+    mirror: bool,
+) -> Result<PathBuf, Error> {
+    let path = output_path(input, "", output_dir, mirror)?;
+    source_path(&path, extension).ok_or_else(|| {
+        Error::Usage(format!(
+            "{} does not have the suffix {extension}",
+            input.display()
+        ))
+    })
+}
+
+/// Returns the path of the output file for `input`.
+///
+/// If `output_dir` is specified, the output file is placed in it. If `mirror`
+/// is also `true`, the directory structure of `input` is preserved under
+/// `output_dir`.
+fn output_path(
+    input: &Path,
+    extension: &str,
+    output_dir: Option<&Path>,
+    mirror: bool,
+) -> Result<PathBuf, Error> {
+    let mut path = match output_dir {
+        Some(dir) if mirror => {
+            let mut path = dir.to_path_buf();
+            for component in input.components() {
+                match component {
+                    Component::Normal(c) => path.push(c),
+                    Component::ParentDir => {
+                        return Err(Error::Usage(format!(
+                            "could not mirror {} because it contains `..`",
+                            input.display()
+                        )));
+                    }
+                    Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+                }
+            }
+            path
+        }
+        Some(dir) => {
+            let name = input.file_name().ok_or_else(|| {
+                Error::Usage(format!("{} does not have a file name", input.display()))
+            })?;
+            dir.join(name)
+        }
+        None => input.to_path_buf(),
+    };
+    path.as_mut_os_string().push(extension);
+    Ok(path)
+}
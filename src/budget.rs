@@ -0,0 +1,301 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    collections::HashSet,
+    fmt::{self, Write as _},
+    fs, io, iter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, bail};
+use byte_unit::{Byte, UnitType};
+
+use crate::glob::Glob;
+
+/// A rule which limits the size of output files.
+///
+/// A rule is written as "<GLOB>=<LIMIT>", where <GLOB> matches output files
+/// in the same way as `--include`, and <LIMIT> is either a size such as
+/// "150 KiB" or a percentage such as "80%" of the original size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Budget {
+    glob: Glob,
+    limit: Limit,
+    rule: String,
+}
+
+/// The limit of a [`Budget`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Limit {
+    /// The maximum compressed size.
+    Size(Byte),
+
+    /// The maximum compressed size as a percentage of the original size.
+    Ratio(f64),
+}
+
+impl Budget {
+    /// Checks an output file at `output` against this budget, and returns the
+    /// violation if it is exceeded.
+    pub fn check(
+        &self,
+        output: &Path,
+        original_size: u64,
+        compressed_size: u64,
+    ) -> Option<Violation> {
+        if !self.glob.matches(output) {
+            return None;
+        }
+        let is_exceeded = match self.limit {
+            Limit::Size(size) => compressed_size > size.as_u64(),
+            #[allow(clippy::cast_precision_loss)]
+            Limit::Ratio(percent) => {
+                compressed_size as f64 > original_size as f64 * percent / 100.0
+            }
+        };
+        is_exceeded.then(|| Violation {
+            output: output.to_path_buf(),
+            original_size,
+            compressed_size,
+            budget: self.rule.clone(),
+        })
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.rule.fmt(f)
+    }
+}
+
+impl FromStr for Budget {
+    type Err = anyhow::Error;
+
+    fn from_str(rule: &str) -> anyhow::Result<Self> {
+        let Some((glob, limit)) = rule.rsplit_once('=') else {
+            bail!("the budget is not in the form of `<GLOB>=<LIMIT>`");
+        };
+        let glob = glob.trim().parse()?;
+        let limit = limit.trim();
+        let limit = if let Some(percent) = limit.strip_suffix('%') {
+            let percent = percent
+                .trim_end()
+                .parse::<f64>()
+                .context("the percentage is invalid")?;
+            if !percent.is_finite() || percent < 0.0 {
+                bail!("the percentage must be a non-negative number");
+            }
+            Limit::Ratio(percent)
+        } else {
+            Limit::Size(Byte::parse_str(limit, true)?)
+        };
+        let rule = rule.trim().into();
+        Ok(Self { glob, limit, rule })
+    }
+}
+
+/// An output file which exceeds a [`Budget`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+    pub output: PathBuf,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub budget: String,
+}
+
+/// Reads budgets from the file at `path`.
+///
+/// Each line of the file is a budget. Empty lines and lines starting with "#"
+/// are ignored.
+pub fn load(path: &Path) -> io::Result<Vec<Budget>> {
+    let content = fs::read_to_string(path)?;
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| {
+            line.parse().map_err(|err: anyhow::Error| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {n}: {err:#}"))
+            })
+        })
+        .collect()
+}
+
+/// Returns the number of output files in `violations`.
+///
+/// An output file which exceeds multiple budgets is counted once.
+pub fn count_files(violations: &[Violation]) -> usize {
+    violations
+        .iter()
+        .map(|violation| &violation.output)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Returns `violations` as a table with a row for each of them.
+pub fn table(violations: &[Violation]) -> String {
+    let header = ["FILE", "SIZE", "RATIO", "BUDGET"].map(String::from);
+    let rows = violations
+        .iter()
+        .map(|violation| {
+            #[allow(clippy::cast_precision_loss)]
+            let ratio = if violation.original_size == 0 {
+                String::from("-")
+            } else {
+                format!(
+                    "{:.2}%",
+                    violation.compressed_size as f64 / violation.original_size as f64 * 100.0
+                )
+            };
+            [
+                violation.output.display().to_string(),
+                format!(
+                    "{:#.2}",
+                    Byte::from(violation.compressed_size).get_appropriate_unit(UnitType::Binary)
+                ),
+                ratio,
+                violation.budget.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut widths = header.each_ref().map(|column| column.chars().count());
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in iter::once(&header).chain(&rows) {
+        let mut line = String::new();
+        for (i, (column, width)) in row.iter().zip(widths).enumerate() {
+            if i > 0 {
+                line.push_str("  ");
+            }
+            // Sizes and ratios are right-aligned.
+            if matches!(i, 1 | 2) {
+                write!(line, "{column:>width$}")
+            } else {
+                write!(line, "{column:<width$}")
+            }
+            .expect("writing to a `String` should not fail");
+        }
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(rule: &str) -> Budget {
+        rule.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_budget() {
+        assert_eq!(
+            budget("app.js.gz=150 KiB").limit,
+            Limit::Size(Byte::from(150 * 1024_u64))
+        );
+        assert_eq!(budget("*.gz = 80%").limit, Limit::Ratio(80.0));
+        assert_eq!(budget(" *.gz=12.5 % ").to_string(), "*.gz=12.5 %");
+    }
+
+    #[test]
+    fn parse_invalid_budget() {
+        assert!("app.js.gz".parse::<Budget>().is_err());
+        assert!("=150KiB".parse::<Budget>().is_err());
+        assert!("app.js.gz=".parse::<Budget>().is_err());
+        assert!("app.js.gz=foo".parse::<Budget>().is_err());
+        assert!("*.gz=-1%".parse::<Budget>().is_err());
+        assert!("*.gz=NaN%".parse::<Budget>().is_err());
+    }
+
+    #[test]
+    fn check_size() {
+        let budget = budget("app.js.gz=100 B");
+        assert_eq!(budget.check(Path::new("public/app.js.gz"), 1000, 100), None);
+        assert_eq!(
+            budget.check(Path::new("public/app.js.gz"), 1000, 101),
+            Some(Violation {
+                output: PathBuf::from("public/app.js.gz"),
+                original_size: 1000,
+                compressed_size: 101,
+                budget: String::from("app.js.gz=100 B")
+            })
+        );
+        assert_eq!(budget.check(Path::new("public/lib.js.gz"), 1000, 101), None);
+    }
+
+    #[test]
+    fn check_ratio() {
+        let budget = budget("*.gz=50%");
+        assert_eq!(budget.check(Path::new("foo.gz"), 100, 50), None);
+        assert!(budget.check(Path::new("foo.gz"), 100, 51).is_some());
+        assert!(budget.check(Path::new("foo.gz"), 0, 20).is_some());
+    }
+
+    #[test]
+    fn load_budgets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("budgets");
+        fs::write(&path, "# Budgets\n\napp.js.gz=150KiB\n*.gz=80%\n").unwrap();
+        assert_eq!(
+            load(&path).unwrap(),
+            [budget("app.js.gz=150KiB"), budget("*.gz=80%")]
+        );
+        fs::write(&path, "app.js.gz=150KiB\nfoo\n").unwrap();
+        let err = load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2: "));
+    }
+
+    #[test]
+    fn count_exceeded_files() {
+        let violation = |output: &str| Violation {
+            output: PathBuf::from(output),
+            original_size: 100,
+            compressed_size: 100,
+            budget: String::from("*=1 B"),
+        };
+        assert_eq!(count_files(&[]), 0);
+        assert_eq!(
+            count_files(&[
+                violation("foo.gz"),
+                violation("foo.gz"),
+                violation("bar.gz")
+            ]),
+            2
+        );
+    }
+
+    #[test]
+    fn format_table() {
+        let violations = [
+            Violation {
+                output: PathBuf::from("public/app.js.gz"),
+                original_size: 4096,
+                compressed_size: 2048,
+                budget: String::from("app.js.gz=1KiB"),
+            },
+            Violation {
+                output: PathBuf::from("a.gz"),
+                original_size: 0,
+                compressed_size: 20,
+                budget: String::from("*.gz=80%"),
+            },
+        ];
+        assert_eq!(
+            table(&violations),
+            "FILE               SIZE   RATIO  BUDGET\n\
+             public/app.js.gz  2 KiB  50.00%  app.js.gz=1KiB\n\
+             a.gz               20 B       -  *.gz=80%\n"
+        );
+    }
+}
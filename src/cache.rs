@@ -0,0 +1,299 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+use sha2::{Digest, Sha256};
+use zopfli::{Format, Options};
+
+/// The version of the format of keys.
+///
+/// This should be changed whenever the compressed data for the same key may
+/// change, such as when Zopfli is upgraded.
+const KEY_VERSION: &str = "rzopfli-cache-1";
+
+/// The statistics of the cache.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// The number of entries.
+    pub entries: usize,
+
+    /// The total size of entries in bytes.
+    pub size: u64,
+}
+
+/// An on-disk cache of compressed data, keyed by the input data and the
+/// compression parameters.
+///
+/// Each entry is stored in a file named after its key. Entries which are used
+/// least recently are evicted first.
+#[derive(Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+impl Cache {
+    /// Creates a new `Cache` in `dir`, which holds up to `max_size` bytes.
+    pub const fn new(dir: PathBuf, max_size: u64) -> Self {
+        Self { dir, max_size }
+    }
+
+    /// Returns the key for the data from `reader` compressed with the given
+    /// parameters.
+    pub fn key(
+        options: &Options,
+        format: Format,
+        chunk_size: usize,
+        mut reader: impl Read,
+    ) -> io::Result<String> {
+        let format = match format {
+            Format::Gzip => "gzip",
+            Format::Zlib => "zlib",
+            Format::Deflate => "deflate",
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(
+            format!(
+                "{KEY_VERSION}\0{format}\0{}\0{}\0{}\0{chunk_size}\0",
+                options.iteration_count,
+                options.iterations_without_improvement,
+                options.maximum_block_splits
+            )
+            .as_bytes(),
+        );
+        let mut buf = [u8::default(); 8192];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => hasher.update(&buf[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        let mut key = String::with_capacity(64);
+        for b in hasher.finalize() {
+            write!(key, "{b:02x}").expect("writing to a `String` should not fail");
+        }
+        Ok(key)
+    }
+
+    /// Returns the path of the entry for `key`.
+    fn path(&self, key: &str) -> PathBuf {
+        let (prefix, rest) = key.split_at(2);
+        self.dir.join(prefix).join(rest)
+    }
+
+    /// Opens the entry for `key`, or returns [`None`] if it does not exist.
+    ///
+    /// The modification time of the entry is updated, so that it is evicted
+    /// later.
+    pub fn get(&self, key: &str) -> Option<File> {
+        let path = self.path(key);
+        let entry = File::open(&path).ok()?;
+        // The entry is opened for writing separately, because the cache may be
+        // shared by users who cannot write to it.
+        if let Ok(f) = File::options().write(true).open(&path) {
+            let _ = f.set_modified(SystemTime::now());
+        }
+        Some(entry)
+    }
+
+    /// Stores the data of the file at `source` as the entry for `key`.
+    ///
+    /// The entry is written to a temporary file first, so that other
+    /// processes never see an incomplete entry.
+    pub fn insert(&self, key: &str, source: &Path) -> io::Result<()> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = self.path(key);
+        let parent = path
+            .parent()
+            .expect("the path of an entry should have a parent");
+        fs::create_dir_all(parent)?;
+        let temp = parent.join(format!(
+            ".{key}.{}.{}.tmp",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = File::create_new(&temp).and_then(|mut f| {
+            io::copy(&mut File::open(source)?, &mut f)?;
+            f.flush()?;
+            fs::rename(&temp, &path)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+
+    /// Returns the paths, the sizes and the modification times of entries.
+    fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut entries = Vec::new();
+        let dirs = match self.dir.read_dir() {
+            Ok(dirs) => dirs,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(entries),
+            Err(err) => return Err(err),
+        };
+        for dir in dirs {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in dir.path().read_dir()? {
+                let entry = entry?;
+                // Temporary files are skipped.
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let metadata = entry.metadata()?;
+                if metadata.is_file() {
+                    entries.push((entry.path(), metadata.len(), metadata.modified()?));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Returns the statistics of the cache.
+    pub fn stats(&self) -> io::Result<Stats> {
+        let entries = self.entries()?;
+        Ok(Stats {
+            entries: entries.len(),
+            size: entries.iter().map(|(_, size, _)| size).sum(),
+        })
+    }
+
+    /// Removes entries which are used least recently until the total size of
+    /// entries does not exceed the maximum size, and returns the number of
+    /// removed entries.
+    pub fn evict(&self) -> io::Result<usize> {
+        let mut entries = self.entries()?;
+        let mut size = entries.iter().map(|(_, size, _)| size).sum::<u64>();
+        entries.sort_unstable_by_key(|&(_, _, modified)| modified);
+        let mut removed = 0;
+        for (path, len, _) in entries {
+            if size <= self.max_size {
+                break;
+            }
+            match fs::remove_file(path) {
+                Ok(()) => removed += 1,
+                // The entry may be removed by another process.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+            size -= len;
+        }
+        Ok(removed)
+    }
+
+    /// Removes all entries, and returns the number of removed entries.
+    pub fn clear(&self) -> io::Result<usize> {
+        let entries = self.entries()?;
+        for (path, ..) in &entries {
+            fs::remove_file(path)?;
+            // The directory is only removed if it is empty.
+            if let Some(parent) = path.parent() {
+                let _ = fs::remove_dir(parent);
+            }
+        }
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU64, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn key() {
+        let options = Options::default();
+        let key = Cache::key(&options, Format::Gzip, 1024, b"foo".as_slice()).unwrap();
+        assert_eq!(key.len(), 64);
+        assert_eq!(
+            key,
+            Cache::key(&options, Format::Gzip, 1024, b"foo".as_slice()).unwrap()
+        );
+        assert_ne!(
+            key,
+            Cache::key(&options, Format::Gzip, 1024, b"bar".as_slice()).unwrap()
+        );
+        assert_ne!(
+            key,
+            Cache::key(&options, Format::Zlib, 1024, b"foo".as_slice()).unwrap()
+        );
+        assert_ne!(
+            key,
+            Cache::key(&options, Format::Gzip, 2048, b"foo".as_slice()).unwrap()
+        );
+        let options = Options {
+            iteration_count: NonZeroU64::new(5).unwrap(),
+            ..Default::default()
+        };
+        assert_ne!(
+            key,
+            Cache::key(&options, Format::Gzip, 1024, b"foo".as_slice()).unwrap()
+        );
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path().join("cache"), u64::MAX);
+        let key = Cache::key(&Options::default(), Format::Gzip, 1024, b"foo".as_slice()).unwrap();
+        assert!(cache.get(&key).is_none());
+        let source = temp_dir.path().join("foo.gz");
+        fs::write(&source, b"bar").unwrap();
+        cache.insert(&key, &source).unwrap();
+        let mut buf = Vec::new();
+        cache.get(&key).unwrap().read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"bar");
+        assert_eq!(
+            cache.stats().unwrap(),
+            Stats {
+                entries: 1,
+                size: 3
+            }
+        );
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.stats().unwrap(), Stats::default());
+    }
+
+    #[test]
+    fn evict() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(temp_dir.path().join("cache"), 4);
+        let source = temp_dir.path().join("foo.gz");
+        fs::write(&source, b"foo").unwrap();
+        let keys = ["a", "b", "c"].map(|data| {
+            Cache::key(&Options::default(), Format::Gzip, 1024, data.as_bytes()).unwrap()
+        });
+        let now = SystemTime::now();
+        for (i, key) in (0..).zip(&keys) {
+            cache.insert(key, &source).unwrap();
+            File::options()
+                .write(true)
+                .open(cache.path(key))
+                .unwrap()
+                .set_modified(now - Duration::from_secs(60 - i))
+                .unwrap();
+        }
+        // The first entry is used most recently.
+        cache.get(&keys[0]).unwrap();
+        assert_eq!(cache.evict().unwrap(), 2);
+        assert!(cache.get(&keys[0]).is_some());
+        assert!(cache.get(&keys[1]).is_none());
+        assert!(cache.get(&keys[2]).is_none());
+    }
+}
@@ -11,24 +11,80 @@ use std::{
 };
 
 use anyhow::bail;
-use clap::{CommandFactory, Parser, ValueEnum, ValueHint};
+use byte_unit::Byte;
+use clap::{Args, CommandFactory, Parser, ValueEnum, ValueHint, value_parser};
 use clap_complete::Generator;
 use simplelog::LevelFilter;
 
+use crate::{budget::Budget, glob::Glob};
+
 #[derive(Debug, Parser)]
 #[allow(clippy::struct_excessive_bools)]
 #[command(version, about, max_term_width(100))]
 pub struct Opt {
+    /// Decompress files instead of compressing them.
+    ///
+    /// The suffix is removed from the input filename to get the output
+    /// filename. This is the default when invoked as unzopfli or runzopfli.
+    #[arg(
+        short,
+        long,
+        overrides_with("decompress"),
+        conflicts_with_all(["verify", "update", "watch", "static_root"])
+    )]
+    pub decompress: bool,
+
     /// Write to standard output, keep original files.
-    #[arg(short('c'), long, conflicts_with("remove"), conflicts_with("suffix"))]
+    #[arg(
+        short('c'),
+        long,
+        overrides_with("stdout"),
+        conflicts_with("remove"),
+        conflicts_with("suffix")
+    )]
     pub stdout: bool,
 
     /// Force compression even if the output file already exists.
     ///
-    /// This option allows you to overwrite existing files.
+    /// This option allows you to overwrite existing files, and to compress
+    /// input files which are already compressed, are not regular files, or
+    /// have other hard links.
     #[arg(short, long)]
     pub force: bool,
 
+    /// Skip input files whose output file is up to date.
+    ///
+    /// An output file is up to date if it is not older than the input file.
+    /// If --checksum-file and --checksum-input are also specified, an output
+    /// file is also up to date if the digest of the input file matches the one
+    /// recorded in the existing checksum file. Output files which are not up
+    /// to date are overwritten.
+    #[arg(short, long, conflicts_with_all(["stdout", "watch", "static_root"]))]
+    pub update: bool,
+
+    /// What to do if the output file already exists.
+    ///
+    /// By default, the input file is not processed and an error is reported
+    /// unless --force is specified.
+    #[arg(
+        long,
+        value_enum,
+        value_name("POLICY"),
+        ignore_case(true),
+        conflicts_with_all(["watch", "static_root"])
+    )]
+    pub overwrite: Option<Overwrite>,
+
+    /// How to name backup files with "--overwrite backup".
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        value_name("METHOD"),
+        ignore_case(true)
+    )]
+    pub backup: Backup,
+
     /// Keep input files.
     ///
     /// This is the default behavior.
@@ -36,9 +92,127 @@ pub struct Opt {
     pub _keep: bool,
 
     /// Remove input files after successful compression.
+    ///
+    /// Input files which have other hard links are skipped unless --force is
+    /// specified, since their data would not be removed. If an input file is a
+    /// symbolic link, the file behind it is also removed.
     #[arg(long("rm"))]
     pub remove: bool,
 
+    /// Also synchronize the directory of input files to disk after removing
+    /// them.
+    ///
+    /// Output files are always synchronized before removing input files, so
+    /// that a crash does not lose both of them. This option makes the removal
+    /// itself durable as well.
+    #[arg(long, requires("remove"))]
+    pub synchronous: bool,
+
+    /// Follow symbolic links given as input files.
+    ///
+    /// By default, symbolic links are skipped.
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Verify output files by decompressing them after compression.
+    ///
+    /// The decompressed data is compared with the original data by their
+    /// digests computed with --checksum-algorithm. If they do not match, the
+    /// output file is removed and the input file is kept even if --rm is
+    /// specified.
+    #[arg(long, conflicts_with("stdout"))]
+    pub verify: bool,
+
+    /// Write the digests of output files to <FILE>.
+    ///
+    /// The digests are written in the format of sha256sum(1) or b3sum(1), so
+    /// the output files can be checked with `sha256sum -c <FILE>`.
+    #[arg(long, value_name("FILE"), value_hint(ValueHint::FilePath))]
+    pub checksum_file: Option<PathBuf>,
+
+    /// Also write the digests of input files to the checksum file.
+    #[arg(long, requires("checksum_file"))]
+    pub checksum_input: bool,
+
+    /// The hash algorithm used for the checksum file.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        value_name("ALGORITHM"),
+        ignore_case(true)
+    )]
+    pub checksum_algorithm: HashAlgorithm,
+
+    /// Write a manifest of output files to <PATH> in JSON.
+    ///
+    /// The manifest maps the path of each input file to its output file, the
+    /// original and compressed sizes, the SHA-256 digest of the output file as
+    /// an `ETag`, the format and the Content-Encoding. If <PATH> already exists,
+    /// the manifest is updated rather than overwritten. The manifest is written
+    /// atomically at the end of the run.
+    #[arg(
+        long,
+        value_name("PATH"),
+        value_hint(ValueHint::FilePath),
+        conflicts_with_all(["stdout", "decompress", "watch"])
+    )]
+    pub manifest: Option<PathBuf>,
+
+    /// Fail if an output file exceeds <BUDGET>.
+    ///
+    /// <BUDGET> is written as "<GLOB>=<LIMIT>". <GLOB> matches the path of
+    /// output files in the same way as --include. <LIMIT> is either the
+    /// maximum compressed size such as "150 KiB", or the maximum compressed
+    /// size as a percentage of the original size such as "80%". This option
+    /// can be specified multiple times. All output files are checked, and
+    /// those which exceed any budget are reported together at the end of the
+    /// run.
+    #[arg(
+        long,
+        value_name("BUDGET"),
+        conflicts_with_all(["stdout", "decompress", "watch"])
+    )]
+    pub budget: Vec<Budget>,
+
+    /// Read budgets from <PATH>.
+    ///
+    /// Each line of <PATH> is a budget in the same form as --budget. Empty
+    /// lines and lines starting with '#' are ignored.
+    #[arg(
+        long,
+        value_name("PATH"),
+        value_hint(ValueHint::FilePath),
+        conflicts_with_all(["stdout", "decompress", "watch"])
+    )]
+    pub budget_file: Option<PathBuf>,
+
+    /// Process files in directories recursively.
+    ///
+    /// Symbolic links in the directories are not followed.
+    #[arg(short, long, conflicts_with_all(["watch", "static_root"]))]
+    pub recursive: bool,
+
+    /// Read the list of input files from <PATH>.
+    ///
+    /// The filenames are separated by newlines, or by NUL characters if --null
+    /// is specified. If "-" is specified, the list will be read from standard
+    /// input. The files are processed after [FILE].
+    #[arg(
+        long,
+        value_name("PATH"),
+        value_hint(ValueHint::FilePath),
+        conflicts_with_all(["watch", "static_root"])
+    )]
+    pub files_from: Option<PathBuf>,
+
+    /// Filenames in the list of input files are separated by NUL characters
+    /// instead of newlines.
+    ///
+    /// This is useful with `find -print0`.
+    #[arg(short('0'), long, requires("files_from"))]
+    pub null: bool,
+
     /// Use <SUFFIX> as the suffix for the target file instead of '.gz',
     /// '.zlib', or '.deflate'.
     ///
@@ -47,12 +221,187 @@ pub struct Opt {
     #[arg(short('S'), long)]
     pub suffix: Option<Suffix>,
 
+    /// Write the compressed data to <FILE>.
+    ///
+    /// This option can only be used with a single input.
+    #[arg(
+        short,
+        long,
+        value_name("FILE"),
+        conflicts_with_all(["stdout", "suffix", "output_dir"]),
+        value_hint(ValueHint::FilePath)
+    )]
+    pub output: Option<PathBuf>,
+
+    /// Write output files into <DIR> instead of next to input files.
+    ///
+    /// <DIR> is created if it does not exist.
+    #[arg(
+        long,
+        value_name("DIR"),
+        conflicts_with("stdout"),
+        value_hint(ValueHint::DirPath)
+    )]
+    pub output_dir: Option<PathBuf>,
+
+    /// Mirror the directory structure of input files under the output
+    /// directory.
+    ///
+    /// Input files whose path contains ".." cannot be mirrored.
+    #[arg(long, requires("output_dir"))]
+    pub mirror: bool,
+
+    /// Watch <DIR> and compress files in it whenever they are written.
+    ///
+    /// Files in <DIR> and its subdirectories are compressed once they are
+    /// closed after writing or moved into <DIR>, overwriting existing output
+    /// files. When a file is removed, its output file is also removed. Files
+    /// whose output file is missing or older are compressed at startup. This
+    /// runs until the program is terminated. This is only supported on Linux.
+    #[arg(
+        long,
+        value_name("DIR"),
+        conflicts_with_all(["stdout", "output", "remove", "checksum_file", "input"]),
+        value_hint(ValueHint::DirPath)
+    )]
+    pub watch: Option<PathBuf>,
+
+    /// Precompress files under <DIR> for serving static files.
+    ///
+    /// Each file under <DIR> whose extension is one of --static-ext is
+    /// compressed only if its output file is missing or older than it. The
+    /// modification time of the output file is set to that of the source file,
+    /// and the source file is always kept. Output files whose source file no
+    /// longer exists are removed.
+    #[arg(
+        long("static"),
+        value_name("DIR"),
+        conflicts_with_all(["stdout", "output", "output_dir", "remove", "checksum_file", "watch", "input"]),
+        value_hint(ValueHint::DirPath)
+    )]
+    pub static_root: Option<PathBuf>,
+
+    /// Extensions of files to precompress with --static.
+    #[arg(
+        long,
+        value_name("EXT"),
+        value_delimiter(','),
+        default_value("css,htm,html,js,json,map,mjs,svg,txt,wasm,xml"),
+        requires("static_root")
+    )]
+    pub static_ext: Vec<String>,
+
+    /// Only process files which match <GLOB>.
+    ///
+    /// This option can be specified multiple times, and files which match any
+    /// of them are processed. A pattern without '/' matches against the
+    /// filename. A pattern with '/' matches against the trailing part of the
+    /// path, and '**' in it matches any number of directories.
+    #[arg(long, value_name("GLOB"))]
+    pub include: Vec<Glob>,
+
+    /// Do not process files which match <GLOB>.
+    ///
+    /// This option can be specified multiple times, and takes precedence over
+    /// --include. The patterns are the same as --include.
+    #[arg(long, value_name("GLOB"))]
+    pub exclude: Vec<Glob>,
+
+    /// Do not process files smaller than <SIZE>.
+    #[arg(long, value_name("SIZE"), value_parser(parse_byte))]
+    pub min_size: Option<Byte>,
+
+    /// Do not process files larger than <SIZE>.
+    #[arg(long, value_name("SIZE"), value_parser(parse_byte))]
+    pub max_size: Option<Byte>,
+
+    #[command(flatten)]
+    pub level: Level,
+
     /// Perform compression for the specified number of iterations.
     ///
     /// Higher numbers produce higher compression ratio at the expense of
-    /// compression speed.
-    #[arg(short, long, default_value("15"), value_name("TIMES"))]
-    pub iteration: NonZeroU64,
+    /// compression speed. By default, the number of iterations of the
+    /// compression level is used, which is 15 for the default level.
+    #[arg(short, long, value_name("TIMES"))]
+    pub iteration: Option<NonZeroU64>,
+
+    /// Limit the memory used for compression to approximately <SIZE>.
+    ///
+    /// The input is compressed in smaller chunks so that the peak memory usage
+    /// does not exceed <SIZE>, at the expense of compression ratio. <SIZE> is
+    /// a size such as "64 MiB". If files are compressed in parallel, <SIZE> is
+    /// divided equally among the jobs. By default, the input is compressed in
+    /// chunks of 1 MB, which uses about 134 MiB of memory.
+    #[arg(long, value_name("SIZE"), value_parser(parse_byte))]
+    pub memory_limit: Option<Byte>,
+
+    /// Set the niceness of the process to <N>.
+    ///
+    /// Higher numbers mean lower scheduling priority. Negative numbers usually
+    /// require elevated privileges.
+    #[arg(
+        long,
+        value_name("N"),
+        allow_negative_numbers(true),
+        value_parser(value_parser!(i32).range(-20..=19))
+    )]
+    pub nice: Option<i32>,
+
+    /// Set the I/O scheduling class of the process.
+    ///
+    /// This is only supported on Linux.
+    #[arg(long, value_enum, value_name("CLASS"), ignore_case(true))]
+    pub io_class: Option<IoClass>,
+
+    /// Limit the CPU usage of compression to approximately <PERCENT> of a CPU.
+    ///
+    /// The compression is paused periodically so that it is busy for at most
+    /// <PERCENT> of the time.
+    #[arg(
+        long,
+        value_name("PERCENT"),
+        value_parser(value_parser!(u8).range(1..=100))
+    )]
+    pub cpu_limit: Option<u8>,
+
+    /// Compress up to <N> files in parallel.
+    ///
+    /// If <N> is 0, the number of available CPUs is used. When run by GNU
+    /// make with a jobserver, a token is acquired from it before compressing
+    /// each file in parallel, so the total number of jobs does not exceed the
+    /// limit of make. Files are compressed one at a time when writing to
+    /// standard output.
+    #[arg(short, long, default_value("1"), value_name("N"))]
+    pub jobs: usize,
+
+    /// Cache compressed data in <DIR>, and reuse it for identical inputs.
+    ///
+    /// Entries are keyed by the content of the input, the format and the
+    /// compression options, so the cache can be shared between directories
+    /// and runs. Data read from standard input is not cached.
+    #[arg(long, value_name("DIR"), value_hint(ValueHint::DirPath))]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Keep the size of the cache to at most <SIZE>.
+    ///
+    /// Entries which are used least recently are removed first.
+    #[arg(
+        long,
+        default_value("1 GiB"),
+        value_name("SIZE"),
+        value_parser(parse_byte)
+    )]
+    pub cache_size: Byte,
+
+    /// Print the number of entries and the total size of the cache specified
+    /// by --cache-dir, and exit.
+    #[arg(long, requires("cache_dir"), conflicts_with("cache_clear"))]
+    pub cache_stats: bool,
+
+    /// Remove all entries from the cache specified by --cache-dir, and exit.
+    #[arg(long, requires("cache_dir"))]
+    pub cache_clear: bool,
 
     /// Output to the specified format.
     #[arg(long, value_enum, default_value_t, ignore_case(true))]
@@ -68,12 +417,36 @@ pub struct Opt {
     )]
     pub log_level: LogLevel,
 
+    /// The format of log records.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        value_name("FORMAT"),
+        ignore_case(true)
+    )]
+    pub log_format: LogFormat,
+
+    /// Write log records to <FILE> instead of standard error.
+    ///
+    /// Log records are appended if <FILE> already exists.
+    #[arg(long, value_name("FILE"), value_hint(ValueHint::FilePath))]
+    pub log_file: Option<PathBuf>,
+
     /// Generate shell completion.
     ///
     /// The completion is output to standard output.
     #[arg(long, value_enum, value_name("SHELL"))]
     pub generate_completion: Option<Shell>,
 
+    /// List the known compressed types of input files which are skipped.
+    ///
+    /// Input files starting with the magic bytes of these types, or whose
+    /// filename already has the suffix, are skipped unless --force is
+    /// specified.
+    #[arg(long)]
+    pub list_compressed_types: bool,
+
     /// Files to compress.
     ///
     /// If [FILE] is not specified, or if "-" is specified, data will be read
@@ -94,6 +467,72 @@ impl Opt {
     }
 }
 
+/// The gzip-compatible compression level.
+///
+/// If multiple levels are specified, the last one is used.
+#[derive(Args, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Level {
+    /// Compress faster, the same as -1.
+    ///
+    /// The compression levels from -1 to -9 are accepted for compatibility with
+    /// gzip(1). Lower levels perform fewer iterations and split the data into
+    /// fewer blocks. The default level is -6.
+    #[arg(short('1'), long, overrides_with_all(LEVELS))]
+    fast: bool,
+
+    #[arg(short('2'), hide(true), overrides_with_all(LEVELS))]
+    level2: bool,
+
+    #[arg(short('3'), hide(true), overrides_with_all(LEVELS))]
+    level3: bool,
+
+    #[arg(short('4'), hide(true), overrides_with_all(LEVELS))]
+    level4: bool,
+
+    #[arg(short('5'), hide(true), overrides_with_all(LEVELS))]
+    level5: bool,
+
+    #[arg(short('6'), hide(true), overrides_with_all(LEVELS))]
+    level6: bool,
+
+    #[arg(short('7'), hide(true), overrides_with_all(LEVELS))]
+    level7: bool,
+
+    #[arg(short('8'), hide(true), overrides_with_all(LEVELS))]
+    level8: bool,
+
+    /// Compress better, the same as -9.
+    #[arg(short('9'), long, overrides_with_all(LEVELS))]
+    best: bool,
+}
+
+/// The IDs of the arguments for compression levels.
+const LEVELS: [&str; 9] = [
+    "fast", "level2", "level3", "level4", "level5", "level6", "level7", "level8", "best",
+];
+
+impl Level {
+    /// Returns the specified compression level, or [`None`] if no level is
+    /// specified.
+    pub fn get(&self) -> Option<u8> {
+        [
+            self.fast,
+            self.level2,
+            self.level3,
+            self.level4,
+            self.level5,
+            self.level6,
+            self.level7,
+            self.level8,
+            self.best,
+        ]
+        .into_iter()
+        .zip(1..)
+        .find_map(|(is_specified, level)| is_specified.then_some(level))
+    }
+}
+
 #[derive(Clone, Debug, ValueEnum)]
 #[allow(clippy::doc_markdown)]
 #[value(rename_all = "lower")]
@@ -142,6 +581,11 @@ impl Generator for Shell {
     }
 }
 
+/// Parses a size such as "64 MiB".
+fn parse_byte(s: &str) -> anyhow::Result<Byte> {
+    Byte::parse_str(s, true).map_err(anyhow::Error::from)
+}
+
 /// The suffix for the target file.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Suffix(String);
@@ -194,6 +638,57 @@ impl From<Format> for zopfli::Format {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum HashAlgorithm {
+    /// SHA-256.
+    #[default]
+    Sha256,
+
+    /// BLAKE3.
+    Blake3,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Overwrite {
+    /// Ask whether to overwrite it if standard input is a terminal, otherwise
+    /// skip the input file.
+    Ask,
+
+    /// Skip the input file without failing.
+    Never,
+
+    /// Overwrite it.
+    Always,
+
+    /// Rename it to a backup file, and write a new output file.
+    Backup,
+
+    /// Write the output file with a free name, such as "foo.txt.1.gz".
+    Rename,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum Backup {
+    /// Append "~" to the filename.
+    Simple,
+
+    /// Append ".~N~" to the filename, where N is the next number.
+    Numbered,
+
+    /// Make numbered backups if they already exist, otherwise simple ones.
+    #[default]
+    Existing,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum IoClass {
+    /// Only perform I/O when no other process needs the disk.
+    Idle,
+
+    /// The default class, with the default priority within the class.
+    BestEffort,
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
 #[value(rename_all = "UPPER")]
 pub enum LogLevel {
@@ -230,8 +725,23 @@ impl From<LogLevel> for LevelFilter {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+
+    /// JSON Lines.
+    Json,
+
+    /// logfmt.
+    Logfmt,
+}
+
 #[cfg(test)]
 mod tests {
+    use std::iter;
+
     use super::*;
 
     #[test]
@@ -239,6 +749,24 @@ mod tests {
         Opt::command().debug_assert();
     }
 
+    #[test]
+    fn level() {
+        let level = |args: &[&str]| {
+            Opt::try_parse_from(iter::once("rzopfli").chain(args.iter().copied()))
+                .unwrap()
+                .level
+                .get()
+        };
+        assert_eq!(level(&[]), None);
+        assert_eq!(level(&["-1"]), Some(1));
+        assert_eq!(level(&["-5"]), Some(5));
+        assert_eq!(level(&["--best"]), Some(9));
+        assert_eq!(level(&["-9", "--fast"]), Some(1));
+        assert_eq!(level(&["-1", "-9c"]), Some(9));
+        assert_eq!(level(&["-3", "-3"]), Some(3));
+        assert_eq!(level(&["--nice", "-1"]), None);
+    }
+
     #[test]
     fn file_name_shell() {
         assert_eq!(Shell::Bash.file_name("rzopfli"), "rzopfli.bash");
@@ -311,6 +839,11 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn default_hash_algorithm() {
+        assert_eq!(HashAlgorithm::default(), HashAlgorithm::Sha256);
+    }
+
     #[test]
     fn default_log_level() {
         assert_eq!(LogLevel::default(), LogLevel::Info);
@@ -325,4 +858,9 @@ mod tests {
         assert_eq!(LevelFilter::from(LogLevel::Debug), LevelFilter::Debug);
         assert_eq!(LevelFilter::from(LogLevel::Trace), LevelFilter::Trace);
     }
+
+    #[test]
+    fn default_log_format() {
+        assert_eq!(LogFormat::default(), LogFormat::Text);
+    }
 }
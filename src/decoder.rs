@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::io::{self, Read, Write};
+
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use zopfli::Format;
+
+/// Decompresses data in `format` from `reader` and writes it to `writer`.
+///
+/// Returns the number of bytes written to `writer`.
+pub fn decompress(format: Format, reader: impl Read, mut writer: impl Write) -> io::Result<u64> {
+    match format {
+        Format::Gzip => io::copy(&mut MultiGzDecoder::new(reader), &mut writer),
+        Format::Zlib => io::copy(&mut ZlibDecoder::new(reader), &mut writer),
+        Format::Deflate => io::copy(&mut DeflateDecoder::new(reader), &mut writer),
+    }
+}
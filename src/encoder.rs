@@ -0,0 +1,258 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    cell::RefCell,
+    io::{self, BufWriter, Read, Write},
+    num::NonZeroU64,
+};
+
+use zopfli::{BlockType, DeflateEncoder, Format, GzipEncoder, Options, ZlibEncoder};
+
+/// The default size of chunks, which is the same as [`zopfli::compress`].
+pub const DEFAULT_CHUNK_SIZE: usize = 1_000_000;
+
+/// The minimum size of chunks.
+///
+/// Smaller chunks cannot make use of a full-sized backreference window.
+const MIN_CHUNK_SIZE: usize = 32 * 1024;
+
+/// The approximate peak memory usage of Zopfli per byte of a chunk.
+///
+/// This was measured with the default options.
+const MEMORY_PER_BYTE: u64 = 128;
+
+/// The approximate memory usage which does not depend on the size of chunks.
+const BASE_MEMORY: u64 = 12 * 1024 * 1024;
+
+/// The smallest memory limit which can be honored.
+pub const MIN_MEMORY_LIMIT: u64 = BASE_MEMORY + MIN_CHUNK_SIZE as u64 * MEMORY_PER_BYTE;
+
+/// The compression level which is used if no level is specified.
+pub const DEFAULT_LEVEL: u8 = 6;
+
+/// Returns the options of Zopfli for the gzip-compatible compression `level`,
+/// which ranges from 1 to 9.
+///
+/// Lower levels perform fewer iterations and split the data into fewer
+/// blocks. [`DEFAULT_LEVEL`] is the same as the default options of Zopfli.
+pub fn level_options(level: u8) -> Options {
+    let (iteration_count, maximum_block_splits) = match level {
+        ..=1 => (1, 3),
+        2 => (2, 5),
+        3 => (3, 7),
+        4 => (5, 9),
+        5 => (10, 12),
+        6 => (15, 15),
+        7 => (25, 15),
+        8 => (50, 15),
+        9.. => (100, 15),
+    };
+    Options {
+        iteration_count: NonZeroU64::new(iteration_count)
+            .expect("the number of iterations should not be zero"),
+        maximum_block_splits,
+        ..Default::default()
+    }
+}
+
+/// Returns the size of chunks so that the peak memory usage of Zopfli does
+/// not exceed `limit`.
+///
+/// The result is never larger than [`DEFAULT_CHUNK_SIZE`].
+pub fn chunk_size(limit: u64) -> usize {
+    usize::try_from(limit.saturating_sub(BASE_MEMORY) / MEMORY_PER_BYTE)
+        .unwrap_or(usize::MAX)
+        .clamp(MIN_CHUNK_SIZE, DEFAULT_CHUNK_SIZE)
+}
+
+/// Compresses data from `reader` and writes it to `writer`.
+///
+/// Unlike [`zopfli::compress`], the data is compressed in chunks of
+/// `chunk_size` bytes. Each chunk is written to `writer` as soon as it is
+/// compressed, so the memory usage does not depend on the size of the input.
+pub fn compress(
+    options: Options,
+    format: Format,
+    chunk_size: usize,
+    reader: impl Read,
+    writer: impl Write,
+) -> io::Result<()> {
+    let error = RefCell::new(None);
+    let sink = Sink {
+        inner: writer,
+        error: &error,
+    };
+    let result = match format {
+        Format::Gzip => {
+            let mut encoder = BufWriter::with_capacity(
+                chunk_size,
+                GzipEncoder::new(options, BlockType::Dynamic, sink)?,
+            );
+            copy(reader, &mut encoder, &error)?;
+            encoder.into_inner()?.finish().map(|_| ())
+        }
+        Format::Zlib => {
+            let mut encoder = BufWriter::with_capacity(
+                chunk_size,
+                ZlibEncoder::new(options, BlockType::Dynamic, sink)?,
+            );
+            copy(reader, &mut encoder, &error)?;
+            encoder.into_inner()?.finish().map(|_| ())
+        }
+        Format::Deflate => {
+            let mut encoder = BufWriter::with_capacity(
+                chunk_size,
+                DeflateEncoder::new(options, BlockType::Dynamic, sink),
+            );
+            copy(reader, &mut encoder, &error)?;
+            encoder.into_inner()?.finish().map(|_| ())
+        }
+    };
+    error.into_inner().map_or(result, Err)
+}
+
+/// Copies data from `reader` to `writer` until EOF or until a write to the
+/// underlying [`Sink`] fails.
+fn copy(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    error: &RefCell<Option<io::Error>>,
+) -> io::Result<()> {
+    let mut buf = [0; 8192];
+    while error.borrow().is_none() {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buf[..n])?;
+    }
+    Ok(())
+}
+
+/// A writer which never returns an error to the encoder.
+///
+/// An encoder of Zopfli is left in an inconsistent state if writing fails,
+/// and finishing it again when it is dropped can panic. Instead, the first
+/// error is stored in `error` and the subsequent data is discarded.
+#[derive(Debug)]
+struct Sink<'a, W> {
+    inner: W,
+    error: &'a RefCell<Option<io::Error>>,
+}
+
+impl<W: Write> Write for Sink<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut error = self.error.borrow_mut();
+        if error.is_none() {
+            if let Err(err) = self.inner.write_all(buf) {
+                *error = Some(err);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut error = self.error.borrow_mut();
+        if error.is_none() {
+            if let Err(err) = self.inner.flush() {
+                *error = Some(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_level_options() {
+        assert_eq!(level_options(DEFAULT_LEVEL), Options::default());
+    }
+
+    #[test]
+    fn level_options_increase_effort() {
+        for level in 1..9 {
+            let (lower, higher) = (level_options(level), level_options(level + 1));
+            assert!(lower.iteration_count < higher.iteration_count);
+            assert!(lower.maximum_block_splits <= higher.maximum_block_splits);
+            // Zero means that the number of blocks is not limited.
+            assert_ne!(lower.maximum_block_splits, 0);
+        }
+    }
+
+    #[test]
+    fn chunk_size_with_limit() {
+        assert_eq!(chunk_size(BASE_MEMORY + MEMORY_PER_BYTE * 65536), 65536);
+    }
+
+    #[test]
+    fn chunk_size_with_too_low_limit() {
+        assert_eq!(chunk_size(0), MIN_CHUNK_SIZE);
+        assert_eq!(chunk_size(MIN_MEMORY_LIMIT - 1), MIN_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn chunk_size_with_too_high_limit() {
+        assert_eq!(chunk_size(u64::MAX), DEFAULT_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn memory_of_default_chunk_size() {
+        // The documentation says that the default chunk size uses about 134
+        // MiB of memory.
+        let memory = BASE_MEMORY + DEFAULT_CHUNK_SIZE as u64 * MEMORY_PER_BYTE;
+        assert_eq!(memory / (1024 * 1024), 134);
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        let data = b"Hello, world!".repeat(256);
+        let mut compressed = Vec::new();
+        compress(
+            Options::default(),
+            Format::Deflate,
+            MIN_CHUNK_SIZE,
+            data.as_slice(),
+            &mut compressed,
+        )
+        .unwrap();
+        assert!(compressed.len() < data.len());
+        let mut buf = Vec::new();
+        flate2::read::DeflateDecoder::new(compressed.as_slice())
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn compress_with_write_error() {
+        struct BrokenPipe;
+
+        impl Write for BrokenPipe {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let data = b"Hello, world!".repeat(256);
+        let err = compress(
+            Options::default(),
+            Format::Gzip,
+            MIN_CHUNK_SIZE,
+            data.as_slice(),
+            BrokenPipe,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
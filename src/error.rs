@@ -0,0 +1,269 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    error, fmt, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use log::error;
+
+use crate::{
+    budget::{self, Violation},
+    logger,
+};
+
+/// The type of an error which is the cause of another error.
+pub type Source = Box<dyn error::Error + Send + Sync>;
+
+/// An error which can occur while running the program.
+#[derive(Debug)]
+pub enum Error {
+    /// The program was used incorrectly.
+    Usage(String),
+
+    /// An input file could not be opened.
+    InputNotFound { path: PathBuf, source: io::Error },
+
+    /// An output file already exists.
+    OutputExists { path: PathBuf, source: io::Error },
+
+    /// An I/O error occurred.
+    Io { context: String, source: io::Error },
+
+    /// The data is invalid, such as compressed data which could not be
+    /// verified.
+    Data { context: String, source: Source },
+
+    /// Some of multiple input files could not be processed.
+    PartialFailure { failed: usize, total: usize },
+
+    /// Some output files exceeded their size budgets.
+    BudgetExceeded(Vec<Violation>),
+
+    /// Standard output was closed by the reader.
+    BrokenPipe,
+}
+
+impl Error {
+    /// Creates a new [`Error::Io`].
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        let context = context.into();
+        Self::Io { context, source }
+    }
+
+    /// Creates a new [`Error::Data`].
+    pub fn data(context: impl Into<String>, source: impl Into<Source>) -> Self {
+        let (context, source) = (context.into(), source.into());
+        Self::Data { context, source }
+    }
+
+    /// Returns the exit code which corresponds to this error.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::Usage(_) => sysexits::ExitCode::Usage.into(),
+            Self::InputNotFound { .. } => sysexits::ExitCode::NoInput.into(),
+            Self::OutputExists { .. } => sysexits::ExitCode::CantCreat.into(),
+            Self::Io { .. } => sysexits::ExitCode::IoErr.into(),
+            Self::Data { .. } => sysexits::ExitCode::DataErr.into(),
+            Self::PartialFailure { .. } => ExitCode::FAILURE,
+            Self::BudgetExceeded(_) => ExitCode::from(3),
+            // The same as the exit status of a process terminated by `SIGPIPE`.
+            Self::BrokenPipe => ExitCode::from(141),
+        }
+    }
+
+    /// Returns this error and its causes as a single line.
+    fn message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = error::Error::source(self);
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        message
+    }
+
+    /// Logs this error, which occurred while processing `file` at `stage`.
+    ///
+    /// This is used for errors of each file, so that they are written in the
+    /// same format and to the same destination as other log records.
+    pub fn log(&self, file: Option<&Path>, stage: &str) {
+        let file = file.map_or_else(|| "-".into(), |p| p.display().to_string());
+        error!(file = file.as_str(), stage; "{}", self.message());
+    }
+
+    /// Reports this error, which terminates the program.
+    ///
+    /// The error and its causes are printed to standard error if log records
+    /// are written as text. They are also logged if log records are structured
+    /// or written to a file, so that the log has the error. Nothing is reported
+    /// for [`Error::BrokenPipe`], like other programs which are terminated by
+    /// `SIGPIPE`.
+    pub fn report(&self) {
+        if matches!(self, Self::BrokenPipe) {
+            return;
+        }
+        if logger::is_structured() || logger::is_written_to_file() {
+            if let Self::BudgetExceeded(violations) = self {
+                for violation in violations {
+                    error!(
+                        file:% = violation.output.display(),
+                        stage = "budget",
+                        original_size = violation.original_size,
+                        compressed_size = violation.compressed_size,
+                        budget = violation.budget.as_str();
+                        "{} exceeds the budget {}",
+                        violation.output.display(),
+                        violation.budget
+                    );
+                }
+            }
+            error!(stage = "exit"; "{}", self.message());
+        }
+        if logger::is_structured() {
+            return;
+        }
+        eprintln!("Error: {self}");
+        if let Self::BudgetExceeded(violations) = self {
+            eprint!("\n{}", budget::table(violations));
+        }
+        let mut causes = Vec::new();
+        let mut source = error::Error::source(self);
+        while let Some(cause) = source {
+            causes.push(cause.to_string());
+            source = cause.source();
+        }
+        match causes.as_slice() {
+            [] => {}
+            [cause] => eprintln!("\nCaused by:\n    {cause}"),
+            causes => {
+                eprintln!("\nCaused by:");
+                for (i, cause) in causes.iter().enumerate() {
+                    eprintln!("    {i}: {cause}");
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(message) => write!(f, "{message}"),
+            Self::InputNotFound { path, .. } | Self::OutputExists { path, .. } => {
+                write!(f, "could not open {}", path.display())
+            }
+            Self::Io { context, .. } | Self::Data { context, .. } => write!(f, "{context}"),
+            Self::PartialFailure { failed, total } => {
+                write!(f, "{failed} of {total} files could not be processed")
+            }
+            Self::BudgetExceeded(violations) => {
+                let count = budget::count_files(violations);
+                write!(f, "{count} files exceeded their size budgets")
+            }
+            Self::BrokenPipe => write!(f, "standard output was closed"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Usage(_)
+            | Self::PartialFailure { .. }
+            | Self::BudgetExceeded(_)
+            | Self::BrokenPipe => None,
+            Self::InputNotFound { source, .. }
+            | Self::OutputExists { source, .. }
+            | Self::Io { source, .. } => Some(source),
+            Self::Data { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code() {
+        assert_eq!(
+            Error::Usage(String::default()).exit_code(),
+            ExitCode::from(64)
+        );
+        assert_eq!(
+            Error::InputNotFound {
+                path: PathBuf::default(),
+                source: io::ErrorKind::NotFound.into()
+            }
+            .exit_code(),
+            ExitCode::from(66)
+        );
+        assert_eq!(
+            Error::OutputExists {
+                path: PathBuf::default(),
+                source: io::ErrorKind::AlreadyExists.into()
+            }
+            .exit_code(),
+            ExitCode::from(73)
+        );
+        assert_eq!(
+            Error::io("", io::ErrorKind::Other.into()).exit_code(),
+            ExitCode::from(74)
+        );
+        assert_eq!(Error::data("", "").exit_code(), ExitCode::from(65));
+        assert_eq!(
+            Error::PartialFailure {
+                failed: 1,
+                total: 2
+            }
+            .exit_code(),
+            ExitCode::FAILURE
+        );
+        assert_eq!(
+            Error::BudgetExceeded(Vec::new()).exit_code(),
+            ExitCode::from(3)
+        );
+        assert_eq!(Error::BrokenPipe.exit_code(), ExitCode::from(141));
+    }
+
+    #[test]
+    fn display_error() {
+        assert_eq!(
+            Error::Usage("standard input is a terminal".into()).to_string(),
+            "standard input is a terminal"
+        );
+        assert_eq!(
+            Error::OutputExists {
+                path: PathBuf::from("foo.gz"),
+                source: io::ErrorKind::AlreadyExists.into()
+            }
+            .to_string(),
+            "could not open foo.gz"
+        );
+        assert_eq!(
+            Error::PartialFailure {
+                failed: 1,
+                total: 2
+            }
+            .to_string(),
+            "1 of 2 files could not be processed"
+        );
+    }
+
+    #[test]
+    fn message() {
+        assert_eq!(
+            Error::io("could not read foo", io::Error::other("bar")).message(),
+            "could not read foo: bar"
+        );
+        assert_eq!(
+            Error::Usage("standard input is a terminal".into()).message(),
+            "standard input is a terminal"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{fmt, iter, path::Path, str::FromStr};
+
+use anyhow::bail;
+
+/// A shell-style wildcard pattern for filtering files.
+///
+/// "*" matches any sequence of characters except "/", "**" matches any
+/// sequence of characters including "/", "?" matches any single character
+/// except "/", and "[...]" matches any character in the brackets, which may be
+/// negated by "!" or "^" and contain ranges such as "a-z".
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Glob(String);
+
+impl Glob {
+    /// Returns `true` if the pattern matches `path`.
+    ///
+    /// The pattern matches if it matches `path`, or `path` with any number of
+    /// leading directories removed. So a pattern without "/" matches against
+    /// the filename.
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy();
+        #[cfg(windows)]
+        let path = path.replace('\\', "/");
+        let pattern = self.0.chars().collect::<Vec<_>>();
+        let text = path.chars().collect::<Vec<_>>();
+        iter_suffixes(&text).any(|text| matches(&pattern, text))
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Glob {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> anyhow::Result<Self> {
+        if pattern.is_empty() {
+            bail!("the pattern is an empty string");
+        }
+        let mut rest = pattern;
+        while let Some(start) = rest.find('[') {
+            let class = &rest[start + 1..];
+            let Some(end) = class_end(class) else {
+                bail!("the pattern has an unclosed `[`");
+            };
+            rest = &class[end + 1..];
+        }
+        Ok(Self(pattern.into()))
+    }
+}
+
+/// Returns `text`, and `text` with each number of leading path components
+/// removed.
+fn iter_suffixes(text: &[char]) -> impl Iterator<Item = &[char]> {
+    let starts = text
+        .iter()
+        .enumerate()
+        .filter(|&(_, &c)| c == '/')
+        .map(|(i, _)| i + 1);
+    iter::once(0).chain(starts).map(|i| &text[i..])
+}
+
+/// Returns the byte offset of the "]" which closes a character class whose
+/// content starts at `s`.
+fn class_end(s: &str) -> Option<usize> {
+    let body = s.strip_prefix(['!', '^']).unwrap_or(s);
+    let skipped = s.len() - body.len();
+    // A "]" at the start of the class is a literal.
+    let first = body.chars().next()?.len_utf8();
+    body[first..].find(']').map(|i| skipped + first + i)
+}
+
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // "**/" also matches no directories.
+            matches(rest, text)
+                || (0..text.len())
+                    .filter(|&i| text[i] == '/')
+                    .any(|i| matches(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| matches(rest, &text[i..])),
+        ['?', rest @ ..] => match text {
+            [c, text @ ..] if *c != '/' => matches(rest, text),
+            _ => false,
+        },
+        ['[', class @ ..] => {
+            let class_str = class.iter().collect::<String>();
+            let Some(end) = class_end(&class_str) else {
+                return false;
+            };
+            let len = class_str[..end].chars().count();
+            match text {
+                [c, text @ ..] if *c != '/' && class_matches(&class[..len], *c) => {
+                    matches(&class[len + 1..], text)
+                }
+                _ => false,
+            }
+        }
+        [p, rest @ ..] => match text {
+            [c, text @ ..] if c == p => matches(rest, text),
+            _ => false,
+        },
+    }
+}
+
+/// Returns `true` if `c` is in the character class `class`, which is the
+/// content between `[` and `]`.
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, mut class) = match class {
+        ['!' | '^', class @ ..] => (true, class),
+        class => (false, class),
+    };
+    let mut found = false;
+    while !class.is_empty() {
+        match class {
+            [start, '-', end, rest @ ..] => {
+                found |= (*start..=*end).contains(&c);
+                class = rest;
+            }
+            [x, rest @ ..] => {
+                found |= *x == c;
+                class = rest;
+            }
+            [] => unreachable!(),
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str) -> Glob {
+        pattern.parse().unwrap()
+    }
+
+    #[test]
+    fn match_filename() {
+        assert!(glob("*.html").matches(Path::new("index.html")));
+        assert!(glob("*.html").matches(Path::new("public/docs/index.html")));
+        assert!(!glob("*.html").matches(Path::new("index.htm")));
+        assert!(!glob("*.html").matches(Path::new("index.html/foo")));
+        assert!(glob("foo?.txt").matches(Path::new("foo1.txt")));
+        assert!(!glob("foo?.txt").matches(Path::new("foo.txt")));
+    }
+
+    #[test]
+    fn match_path() {
+        assert!(glob("vendor/*.js").matches(Path::new("public/vendor/foo.js")));
+        assert!(!glob("vendor/*.js").matches(Path::new("public/vendor/foo/bar.js")));
+        assert!(glob("vendor/**").matches(Path::new("public/vendor/foo/bar.js")));
+        assert!(glob("public/**/*.js").matches(Path::new("public/foo.js")));
+        assert!(glob("public/**/*.js").matches(Path::new("public/foo/bar/baz.js")));
+        assert!(!glob("public/**/*.js").matches(Path::new("public/foo.css")));
+    }
+
+    #[test]
+    fn match_class() {
+        assert!(glob("[ab].txt").matches(Path::new("a.txt")));
+        assert!(!glob("[ab].txt").matches(Path::new("c.txt")));
+        assert!(glob("[!ab].txt").matches(Path::new("c.txt")));
+        assert!(glob("[^ab].txt").matches(Path::new("c.txt")));
+        assert!(glob("file[0-9].txt").matches(Path::new("file7.txt")));
+        assert!(!glob("file[0-9].txt").matches(Path::new("filex.txt")));
+        assert!(glob("[]]").matches(Path::new("]")));
+    }
+
+    #[test]
+    fn parse_invalid_glob() {
+        assert!("".parse::<Glob>().is_err());
+        assert!("[a".parse::<Glob>().is_err());
+        assert!("[]".parse::<Glob>().is_err());
+        assert!("a]".parse::<Glob>().is_ok());
+        assert!("[[]".parse::<Glob>().is_ok());
+    }
+}
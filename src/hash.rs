@@ -0,0 +1,248 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::cli::HashAlgorithm;
+
+/// A hasher which computes the digest of data using [`HashAlgorithm`].
+#[derive(Debug)]
+pub enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    /// Creates a new `Hasher`.
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+        }
+    }
+
+    /// Processes the data.
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// Returns the digest of the data processed so far.
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+            Self::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A reader which computes the digest of the data read through it.
+#[derive(Debug)]
+pub struct HashReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R> HashReader<R> {
+    /// Creates a new `HashReader`.
+    pub fn new(inner: R, algorithm: HashAlgorithm) -> Self {
+        let hasher = Hasher::new(algorithm);
+        Self { inner, hasher }
+    }
+
+    /// Returns the digest of the data read so far.
+    pub fn finalize(self) -> Vec<u8> {
+        self.hasher.finalize()
+    }
+}
+
+impl<R: Read> Read for HashReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A writer which computes the digest of the data written through it.
+#[derive(Debug)]
+pub struct HashWriter<W> {
+    inner: W,
+    hasher: Hasher,
+}
+
+impl<W> HashWriter<W> {
+    /// Creates a new `HashWriter`.
+    pub fn new(inner: W, algorithm: HashAlgorithm) -> Self {
+        let hasher = Hasher::new(algorithm);
+        Self { inner, hasher }
+    }
+
+    /// Returns the digest of the data written so far.
+    pub fn finalize(self) -> Vec<u8> {
+        self.hasher.finalize()
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns a line of the checksum file for `digest` of the file at `path`.
+///
+/// The line is in the format of `sha256sum` and `b3sum`. If `path` is
+/// [`None`], "-" is used as the filename, which represents standard input or
+/// standard output.
+pub fn checksum_line(digest: &[u8], path: Option<&Path>) -> String {
+    let name = path.map_or_else(|| "-".into(), |p| p.to_string_lossy());
+    // Like coreutils, a filename containing a backslash or a newline is escaped
+    // and the line is prefixed with a backslash.
+    let escaped = name.contains(['\\', '\n']);
+    let mut line = String::from(if escaped { "\\" } else { "" });
+    for b in digest {
+        write!(line, "{b:02x}").expect("writing to a `String` should not fail");
+    }
+    line.push_str("  ");
+    if escaped {
+        line.push_str(&name.replace('\\', "\\\\").replace('\n', "\\n"));
+    } else {
+        line.push_str(&name);
+    }
+    line.push('\n');
+    line
+}
+
+/// Parses a line of the checksum file, and returns the digest and the
+/// filename.
+///
+/// This is the inverse of [`checksum_line`]. [`None`] is returned if the line
+/// is malformed.
+pub fn parse_checksum_line(line: &str) -> Option<(Vec<u8>, String)> {
+    let (escaped, line) = line
+        .strip_prefix('\\')
+        .map_or((false, line), |line| (true, line));
+    let (digest, name) = line.split_once("  ")?;
+    if digest.is_empty() || digest.len() % 2 != 0 {
+        return None;
+    }
+    let digest = (0..digest.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digest.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    if !escaped {
+        return Some((digest, name.into()));
+    }
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '\\' => unescaped.push('\\'),
+                'n' => unescaped.push('\n'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+    Some((digest, unescaped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_reader() {
+        let mut reader = HashReader::new(b"Hello, world!".as_slice(), HashAlgorithm::Sha256);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello, world!");
+        assert_eq!(
+            reader.finalize(),
+            Sha256::digest(b"Hello, world!").as_slice()
+        );
+    }
+
+    #[test]
+    fn hash_writer() {
+        let mut writer = HashWriter::new(Vec::new(), HashAlgorithm::Blake3);
+        writer.write_all(b"Hello, world!").unwrap();
+        assert_eq!(writer.inner, b"Hello, world!");
+        assert_eq!(writer.finalize(), blake3::hash(b"Hello, world!").as_bytes());
+    }
+
+    #[test]
+    fn checksum_line_with_path() {
+        assert_eq!(
+            checksum_line(&[0x01, 0xab], Some(Path::new("foo.txt.gz"))),
+            "01ab  foo.txt.gz\n"
+        );
+    }
+
+    #[test]
+    fn checksum_line_without_path() {
+        assert_eq!(checksum_line(&[0x01, 0xab], None), "01ab  -\n");
+    }
+
+    #[test]
+    fn checksum_line_with_escaped_path() {
+        assert_eq!(
+            checksum_line(&[0x01, 0xab], Some(Path::new("foo\nbar\\baz"))),
+            "\\01ab  foo\\nbar\\\\baz\n"
+        );
+    }
+
+    #[test]
+    fn parse_checksum_line_with_path() {
+        assert_eq!(
+            parse_checksum_line("01ab  foo.txt.gz"),
+            Some((vec![0x01, 0xab], "foo.txt.gz".into()))
+        );
+    }
+
+    #[test]
+    fn parse_checksum_line_with_escaped_path() {
+        assert_eq!(
+            parse_checksum_line("\\01ab  foo\\nbar\\\\baz"),
+            Some((vec![0x01, 0xab], "foo\nbar\\baz".into()))
+        );
+    }
+
+    #[test]
+    fn parse_invalid_checksum_line() {
+        assert!(parse_checksum_line("01ab foo.txt.gz").is_none());
+        assert!(parse_checksum_line("01a  foo.txt.gz").is_none());
+        assert!(parse_checksum_line("01zz  foo.txt.gz").is_none());
+        assert!(parse_checksum_line("\\01ab  foo\\tbar").is_none());
+    }
+}
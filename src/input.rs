@@ -3,8 +3,9 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use std::{
-    fs::File,
+    fs::{File, Metadata},
     io::{self, Read, Stdin},
+    path::PathBuf,
 };
 
 #[derive(Debug)]
@@ -21,3 +22,102 @@ impl Read for Input {
         }
     }
 }
+
+/// Returns the number of hard links to the file described by `metadata`.
+#[cfg(unix)]
+pub fn link_count(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.nlink()
+}
+
+/// Returns the number of hard links to the file described by `metadata`.
+///
+/// This always returns 1 on non-Unix platforms.
+#[cfg(not(unix))]
+pub fn link_count(_: &Metadata) -> u64 {
+    1
+}
+
+/// Returns the device and inode numbers which identify the file described by
+/// `metadata`.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+pub fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Returns the device and inode numbers which identify the file described by
+/// `metadata`.
+///
+/// This always returns [`None`] on non-Unix platforms.
+#[cfg(not(unix))]
+pub const fn file_id(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Reads the list of input files from `reader`.
+///
+/// The filenames are separated by newlines, or by NUL characters if `null` is
+/// `true`. Empty filenames are ignored.
+pub fn read_file_list(mut reader: impl Read, null: bool) -> io::Result<Vec<PathBuf>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let separator = if null { b'\0' } else { b'\n' };
+    buf.split(|&b| b == separator)
+        .map(|name| match name {
+            [name @ .., b'\r'] if !null => name,
+            name => name,
+        })
+        .filter(|name| !name.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+fn path_from_bytes(bytes: &[u8]) -> io::Result<PathBuf> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    Ok(OsStr::from_bytes(bytes).into())
+}
+
+/// Non-UTF-8 filenames cannot be represented on non-Unix platforms.
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> io::Result<PathBuf> {
+    std::str::from_utf8(bytes)
+        .map(PathBuf::from)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_file_list_separated_by_newlines() {
+        assert_eq!(
+            read_file_list(b"foo.txt\nbar baz.txt\r\n\nqux\0.txt".as_slice(), false).unwrap(),
+            [
+                PathBuf::from("foo.txt"),
+                PathBuf::from("bar baz.txt"),
+                PathBuf::from("qux\0.txt")
+            ]
+        );
+    }
+
+    #[test]
+    fn read_file_list_separated_by_nul() {
+        assert_eq!(
+            read_file_list(b"foo.txt\0bar\nbaz.txt\0\0".as_slice(), true).unwrap(),
+            [PathBuf::from("foo.txt"), PathBuf::from("bar\nbaz.txt")]
+        );
+    }
+
+    #[test]
+    fn read_empty_file_list() {
+        assert!(read_file_list(b"".as_slice(), false).unwrap().is_empty());
+    }
+}
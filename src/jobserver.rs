@@ -0,0 +1,240 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    env,
+    fs::File,
+    io::{self, Read, Write},
+    path::PathBuf,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+/// How long to wait for a token before checking whether it is still needed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How to connect to the jobserver of GNU make.
+#[derive(Debug, Eq, PartialEq)]
+enum Auth {
+    /// A named pipe, which is used by GNU make 4.4 or later.
+    Fifo(PathBuf),
+
+    /// A pair of file descriptors of an anonymous pipe which are inherited
+    /// from GNU make.
+    Fds(u32, u32),
+}
+
+/// Returns how to connect to the jobserver from the value of `MAKEFLAGS`.
+///
+/// If the option is specified more than once, the last one is used.
+fn parse_makeflags(flags: &str) -> Option<Auth> {
+    let auth = flags
+        .split_ascii_whitespace()
+        .filter_map(|arg| {
+            arg.strip_prefix("--jobserver-auth=")
+                .or_else(|| arg.strip_prefix("--jobserver-fds="))
+        })
+        .next_back()?;
+    if let Some(path) = auth.strip_prefix("fifo:") {
+        return Some(Auth::Fifo(path.into()));
+    }
+    let (read, write) = auth.split_once(',')?;
+    Some(Auth::Fds(read.parse().ok()?, write.parse().ok()?))
+}
+
+/// A client of the jobserver of GNU make.
+///
+/// Each token is a byte in a pipe which is shared by all jobs. A job reads a
+/// byte before starting additional work and writes it back afterwards. Every
+/// process also has an implicit token, which does not need to be acquired.
+#[derive(Debug)]
+pub struct Client {
+    read: File,
+    write: File,
+    held: Mutex<Vec<u8>>,
+}
+
+impl Client {
+    /// Connects to the jobserver specified by `MAKEFLAGS`.
+    ///
+    /// Returns [`None`] if no jobserver is specified.
+    ///
+    /// This must be called before the program opens any file. Otherwise, if
+    /// make did not pass the file descriptors on, they may have been reused by
+    /// the program.
+    pub fn from_env() -> Option<io::Result<Self>> {
+        let flags = env::var("MAKEFLAGS").ok()?;
+        parse_makeflags(&flags).map(Self::connect)
+    }
+
+    #[cfg(unix)]
+    fn connect(auth: Auth) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        let (read, write) = match auth {
+            Auth::Fifo(path) => {
+                let read = File::options().read(true).write(true).open(path)?;
+                let write = read.try_clone()?;
+                (read, write)
+            }
+            // The inherited file descriptors are reopened, since they cannot be
+            // used directly without `unsafe`.
+            Auth::Fds(read, write) => {
+                let read = File::open(format!("/dev/fd/{read}"))?;
+                let write = File::options()
+                    .write(true)
+                    .open(format!("/dev/fd/{write}"))?;
+                (read, write)
+            }
+        };
+        // If make did not pass the file descriptors on, they may refer to
+        // something other than the pipe.
+        for file in [&read, &write] {
+            if !file.metadata()?.file_type().is_fifo() {
+                return Err(io::Error::other("the jobserver is not a pipe"));
+            }
+        }
+        // Reading never blocks, since another process may take the token after
+        // polling. This only affects the file opened here, not that of make.
+        rustix::io::ioctl_fionbio(&read, true)?;
+        Ok(Self {
+            read,
+            write,
+            held: Mutex::default(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn connect(_: Auth) -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Acquires a token, blocking until one is available or `is_cancelled`
+    /// returns `true`.
+    ///
+    /// Returns [`None`] if cancelled. The token must be given back with
+    /// [`Client::release`].
+    #[allow(clippy::significant_drop_tightening)]
+    pub fn acquire(&self, is_cancelled: impl Fn() -> bool) -> io::Result<Option<u8>> {
+        let mut token = [u8::default()];
+        loop {
+            if is_cancelled() {
+                return Ok(None);
+            }
+            if !self.wait(POLL_INTERVAL)? {
+                continue;
+            }
+            // The token is recorded as soon as it is read, so that it is given
+            // back by `Client::release_all`.
+            let mut held = self.held();
+            match (&self.read).read(&mut token) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => {
+                    held.push(token[0]);
+                    return Ok(Some(token[0]));
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+                    ) => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Waits until a token may be available or `timeout` elapses, and returns
+    /// whether a token may be available.
+    #[cfg(unix)]
+    fn wait(&self, timeout: Duration) -> io::Result<bool> {
+        use rustix::event::{PollFd, PollFlags, Timespec};
+
+        let timeout = Timespec::try_from(timeout).map_err(io::Error::other)?;
+        let mut fds = [PollFd::new(&self.read, PollFlags::IN)];
+        match rustix::event::poll(&mut fds, Some(&timeout)) {
+            Ok(n) => Ok(n > 0),
+            Err(rustix::io::Errno::INTR) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Waits until a token may be available.
+    ///
+    /// A client is never connected on non-Unix platforms, so this just returns
+    /// `true`.
+    #[cfg(not(unix))]
+    #[allow(clippy::unnecessary_wraps)]
+    fn wait(&self, _: Duration) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    /// Releases a token which was acquired with [`Client::acquire`].
+    ///
+    /// GNU make expects the same byte to be written back. A token which has
+    /// already been released by [`Client::release_all`] is not written again.
+    #[allow(clippy::significant_drop_tightening)]
+    pub fn release(&self, token: u8) -> io::Result<()> {
+        let mut held = self.held();
+        let Some(i) = held.iter().position(|&t| t == token) else {
+            return Ok(());
+        };
+        (&self.write).write_all(&[token])?;
+        held.swap_remove(i);
+        Ok(())
+    }
+
+    /// Releases all tokens which have been acquired and not released yet.
+    #[allow(clippy::significant_drop_tightening)]
+    pub fn release_all(&self) -> io::Result<()> {
+        let mut held = self.held();
+        (&self.write).write_all(&held)?;
+        held.clear();
+        Ok(())
+    }
+
+    /// Locks the tokens which have been acquired and not released yet.
+    fn held(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.held.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_makeflags_with_fifo() {
+        assert_eq!(
+            parse_makeflags(" -j4 --jobserver-auth=fifo:/tmp/GMfifo1234"),
+            Some(Auth::Fifo(PathBuf::from("/tmp/GMfifo1234")))
+        );
+    }
+
+    #[test]
+    fn parse_makeflags_with_fds() {
+        assert_eq!(
+            parse_makeflags("-j --jobserver-auth=3,4"),
+            Some(Auth::Fds(3, 4))
+        );
+        assert_eq!(
+            parse_makeflags("-j --jobserver-fds=5,6 -j"),
+            Some(Auth::Fds(5, 6))
+        );
+    }
+
+    #[test]
+    fn parse_makeflags_with_last_auth() {
+        assert_eq!(
+            parse_makeflags("--jobserver-auth=3,4 --jobserver-auth=fifo:foo"),
+            Some(Auth::Fifo(PathBuf::from("foo")))
+        );
+    }
+
+    #[test]
+    fn parse_makeflags_without_auth() {
+        assert_eq!(parse_makeflags(""), None);
+        assert_eq!(parse_makeflags("-k -j4"), None);
+        assert_eq!(parse_makeflags("--jobserver-auth=-1,-1"), None);
+    }
+}
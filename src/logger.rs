@@ -0,0 +1,237 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    fs::File,
+    io::{self, Stderr, Write},
+    sync::{Mutex, OnceLock},
+};
+
+use log::{
+    Log, Metadata, Record, SetLoggerError,
+    kv::{self, Key, Value, VisitSource},
+};
+use serde_json::{Map, Number};
+use simplelog::{
+    ColorChoice, Config, LevelFilter, SimpleLogger, TermLogger, TerminalMode, WriteLogger,
+};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::cli::LogFormat;
+
+/// The destination of the global logger, which is whether log records are
+/// structured and whether they are written to a file.
+static DESTINATION: OnceLock<(bool, bool)> = OnceLock::new();
+
+/// Initializes the global logger.
+///
+/// If `file` is [`None`], log records are written to standard error.
+pub fn init(
+    level: LevelFilter,
+    format: LogFormat,
+    file: Option<File>,
+) -> Result<(), SetLoggerError> {
+    let _ = DESTINATION.set((format != LogFormat::Text, file.is_some()));
+    match (format, file) {
+        (LogFormat::Text, None) => TermLogger::init(
+            level,
+            Config::default(),
+            TerminalMode::Stderr,
+            ColorChoice::Auto,
+        )
+        .or_else(|_| SimpleLogger::init(level, Config::default())),
+        (LogFormat::Text, Some(file)) => WriteLogger::init(level, Config::default(), file),
+        (format, file) => {
+            let writer = file.map_or_else(|| Writer::Stderr(io::stderr()), Writer::File);
+            let logger = StructuredLogger {
+                level,
+                format,
+                writer: Mutex::new(writer),
+            };
+            log::set_boxed_logger(Box::new(logger))?;
+            log::set_max_level(level);
+            Ok(())
+        }
+    }
+}
+
+/// Returns `true` if log records are written as JSON or logfmt.
+///
+/// This is `false` if the logger is not initialized.
+pub fn is_structured() -> bool {
+    DESTINATION
+        .get()
+        .is_some_and(|&(is_structured, _)| is_structured)
+}
+
+/// Returns `true` if log records are written to a file.
+pub fn is_written_to_file() -> bool {
+    DESTINATION.get().is_some_and(|&(_, is_file)| is_file)
+}
+
+/// The destination of log records.
+#[derive(Debug)]
+enum Writer {
+    Stderr(Stderr),
+    File(File),
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Self::Stderr(ref mut stderr) => stderr.write(buf),
+            Self::File(ref mut file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Self::Stderr(ref mut stderr) => stderr.flush(),
+            Self::File(ref mut file) => file.flush(),
+        }
+    }
+}
+
+/// A logger which writes each record as a line of JSON or logfmt.
+///
+/// Key-value pairs of a record are written as separate fields.
+#[derive(Debug)]
+struct StructuredLogger {
+    level: LevelFilter,
+    format: LogFormat,
+    writer: Mutex<Writer>,
+}
+
+impl StructuredLogger {
+    /// Returns the line which represents `record`.
+    fn format(&self, record: &Record<'_>) -> String {
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("the current time should be formattable as RFC 3339");
+        let mut fields = Fields::default();
+        // Failures are impossible since the visitor never returns an error.
+        let _ = record.key_values().visit(&mut fields);
+        match self.format {
+            LogFormat::Json => {
+                let mut map = Map::new();
+                map.insert("timestamp".into(), timestamp.into());
+                map.insert("level".into(), record.level().as_str().into());
+                map.insert("target".into(), record.target().into());
+                map.insert("message".into(), record.args().to_string().into());
+                for (key, value) in fields.0 {
+                    map.insert(key, value);
+                }
+                serde_json::Value::Object(map).to_string()
+            }
+            LogFormat::Logfmt | LogFormat::Text => {
+                let mut pairs = vec![
+                    ("time".into(), timestamp),
+                    ("level".into(), record.level().as_str().to_lowercase()),
+                    ("target".into(), record.target().into()),
+                    ("msg".into(), record.args().to_string()),
+                ];
+                for (key, value) in fields.0 {
+                    let value = match value {
+                        serde_json::Value::String(s) => s,
+                        value => value.to_string(),
+                    };
+                    pairs.push((key, value));
+                }
+                pairs
+                    .iter()
+                    .map(|(key, value)| format!("{key}={}", logfmt_value(value)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        }
+    }
+}
+
+impl Log for StructuredLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = self.format(record);
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "{line}");
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writer.flush();
+        }
+    }
+}
+
+/// Key-value pairs of a record.
+#[derive(Debug, Default)]
+struct Fields(Vec<(String, serde_json::Value)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        #[allow(clippy::option_if_let_else)]
+        let value = if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(n) = value.to_f64().and_then(Number::from_f64) {
+            n.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.push((key.as_str().into(), value));
+        Ok(())
+    }
+}
+
+/// Returns `value` quoted if necessary for logfmt.
+fn logfmt_value(value: &str) -> String {
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '=' | '\\'))
+    {
+        return value.into();
+    }
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logfmt_value_without_quote() {
+        assert_eq!(logfmt_value("foo.txt"), "foo.txt");
+        assert_eq!(logfmt_value("123"), "123");
+    }
+
+    #[test]
+    fn logfmt_value_with_quote() {
+        assert_eq!(logfmt_value(""), r#""""#);
+        assert_eq!(logfmt_value("foo bar"), r#""foo bar""#);
+        assert_eq!(logfmt_value("a=b"), r#""a=b""#);
+        assert_eq!(logfmt_value("a\"b\\c\n"), r#""a\"b\\c\n""#);
+    }
+}
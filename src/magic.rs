@@ -0,0 +1,207 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+use flate2::{Decompress, FlushDecompress, Status};
+
+/// The maximum number of bytes needed to detect a compressed type.
+///
+/// This is the length of the prefix which is inflated to confirm a zlib
+/// header, since the other types only need a few magic bytes.
+pub const HEADER_LEN: usize = 256;
+
+/// A type of data which is already compressed, so compressing it again is
+/// pointless.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compressed {
+    Gzip,
+    Zlib,
+    Zstd,
+    Xz,
+    Bzip2,
+    Png,
+    Jpeg,
+}
+
+impl Compressed {
+    /// All known compressed types.
+    pub const ALL: [Self; 7] = [
+        Self::Gzip,
+        Self::Zlib,
+        Self::Zstd,
+        Self::Xz,
+        Self::Bzip2,
+        Self::Png,
+        Self::Jpeg,
+    ];
+
+    /// Returns the magic bytes at the start of data of this type.
+    ///
+    /// zlib has no fixed magic bytes, so its header is checked separately.
+    pub const fn magic(self) -> &'static [u8] {
+        match self {
+            Self::Gzip => b"\x1f\x8b",
+            Self::Zlib => b"",
+            Self::Zstd => b"\x28\xb5\x2f\xfd",
+            Self::Xz => b"\xfd7zXZ\x00",
+            Self::Bzip2 => b"BZh",
+            Self::Png => b"\x89PNG",
+            Self::Jpeg => b"\xff\xd8\xff",
+        }
+    }
+
+    /// Detects the compressed type of data starting with `header`.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| match kind {
+            Self::Zlib => is_zlib(header),
+            kind => header.starts_with(kind.magic()),
+        })
+    }
+
+    /// Reads the start of `reader` and detects its compressed type.
+    pub fn read(reader: impl Read) -> io::Result<Option<Self>> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        reader.take(HEADER_LEN as u64).read_to_end(&mut header)?;
+        Ok(Self::detect(&header))
+    }
+}
+
+impl fmt::Display for Compressed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Gzip => "gzip",
+            Self::Zlib => "zlib",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+            Self::Bzip2 => "bzip2",
+            Self::Png => "PNG",
+            Self::Jpeg => "JPEG",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Returns `true` if `header` is the start of zlib data using DEFLATE.
+///
+/// The two-byte zlib header (see RFC 1950) also matches ordinary text such as
+/// "x = 1", so the data is confirmed by inflating it. It must either end within
+/// `header`, or fill `header` without any error.
+fn is_zlib(header: &[u8]) -> bool {
+    let is_zlib_header = match header {
+        [cmf, flg, ..] => {
+            cmf & 0x0f == 8 && cmf >> 4 <= 7 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0
+        }
+        _ => false,
+    };
+    if !is_zlib_header {
+        return false;
+    }
+    let mut decompress = Decompress::new(true);
+    let mut buf = [u8::default(); 1024];
+    loop {
+        let consumed = usize::try_from(decompress.total_in())
+            .expect("the consumed size should be at most the header length");
+        match decompress.decompress(&header[consumed..], &mut buf, FlushDecompress::None) {
+            Ok(Status::StreamEnd) => return true,
+            Ok(Status::Ok) => {}
+            // No more progress can be made, since the header ends.
+            Ok(Status::BufError) => return header.len() == HEADER_LEN,
+            Err(_) => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{
+        Compression,
+        write::{GzEncoder, ZlibEncoder},
+    };
+
+    use super::*;
+
+    #[test]
+    fn detect() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"foo").unwrap();
+        assert_eq!(
+            Compressed::detect(&encoder.finish().unwrap()),
+            Some(Compressed::Gzip)
+        );
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"foo").unwrap();
+        assert_eq!(
+            Compressed::detect(&encoder.finish().unwrap()),
+            Some(Compressed::Zlib)
+        );
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(include_bytes!("../tests/data/LICENSES/CC-BY-4.0.txt"))
+            .unwrap();
+        assert_eq!(
+            Compressed::detect(&encoder.finish().unwrap()[..HEADER_LEN]),
+            Some(Compressed::Zlib)
+        );
+        assert_eq!(
+            Compressed::detect(b"\x28\xb5\x2f\xfd\x00"),
+            Some(Compressed::Zstd)
+        );
+        assert_eq!(
+            Compressed::detect(b"\xfd7zXZ\x00\x00"),
+            Some(Compressed::Xz)
+        );
+        assert_eq!(Compressed::detect(b"BZh91AY"), Some(Compressed::Bzip2));
+        assert_eq!(
+            Compressed::detect(b"\x89PNG\r\n\x1a\n"),
+            Some(Compressed::Png)
+        );
+        assert_eq!(
+            Compressed::detect(b"\xff\xd8\xff\xe0"),
+            Some(Compressed::Jpeg)
+        );
+    }
+
+    #[test]
+    fn detect_uncompressed() {
+        assert_eq!(Compressed::detect(b""), None);
+        assert_eq!(Compressed::detect(b"\x1f"), None);
+        assert_eq!(Compressed::detect(b"Hello, world!"), None);
+        assert_eq!(Compressed::detect(b"xyz"), None);
+        // Text which happens to have a valid zlib header is not compressed.
+        assert_eq!(Compressed::detect(b"80"), None);
+        assert_eq!(Compressed::detect(b"80,120"), None);
+        assert_eq!(Compressed::detect(b"x = 1"), None);
+        let text = include_bytes!("../tests/data/LICENSES/CC-BY-4.0.txt");
+        for header in [b"80,120\n".as_slice(), b"x = 1\n", b"x\x9c"] {
+            assert_eq!(
+                Compressed::detect(&[header, text].concat()[..HEADER_LEN]),
+                None
+            );
+        }
+        // Truncated zlib data is not confirmed.
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"foo").unwrap();
+        let data = encoder.finish().unwrap();
+        assert_eq!(Compressed::detect(&data[..data.len() - 1]), None);
+        assert_eq!(
+            Compressed::detect(include_bytes!("../tests/data/LICENSES/CC-BY-4.0.txt")),
+            None
+        );
+    }
+
+    #[test]
+    fn read() {
+        assert_eq!(
+            Compressed::read(b"BZh91AY&SY".as_slice()).unwrap(),
+            Some(Compressed::Bzip2)
+        );
+        assert_eq!(Compressed::read(b"BZ".as_slice()).unwrap(), None);
+    }
+}
@@ -3,21 +3,59 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 mod app;
+mod budget;
+mod cache;
 mod cli;
+mod decoder;
+mod encoder;
+mod error;
+mod glob;
+mod hash;
 mod input;
+mod jobserver;
+mod logger;
+mod magic;
+mod manifest;
 mod output;
+mod overwrite;
+mod priority;
+mod signal;
+mod throttle;
+mod walk;
+mod watch;
 
-use std::{io, process::ExitCode};
+use std::{env, ffi::OsStr, path::Path, process::ExitCode};
+
+/// Returns the options implied by the name which the program is invoked as.
+///
+/// Like gunzip(1) and zcat(1), the program decompresses by default when
+/// invoked as unzopfli or runzopfli, and also writes to standard output when
+/// invoked as zopfcat.
+fn implied_args(program: Option<&OsStr>) -> &'static [&'static str] {
+    match program
+        .map(Path::new)
+        .and_then(Path::file_stem)
+        .and_then(OsStr::to_str)
+    {
+        Some("unzopfli" | "runzopfli") => &["--decompress"],
+        Some("zopfcat") => &["--decompress", "--stdout"],
+        _ => &[],
+    }
+}
 
 fn main() -> ExitCode {
-    match app::run() {
+    let mut args = env::args_os();
+    let program = args.next();
+    let implied = implied_args(program.as_deref());
+    let args = program
+        .into_iter()
+        .chain(implied.iter().map(Into::into))
+        .chain(args);
+    match app::run(args) {
         Ok(()) => ExitCode::SUCCESS,
         Err(err) => {
-            eprintln!("Error: {err:?}");
-            if let Some(e) = err.downcast_ref::<io::Error>() {
-                return sysexits::ExitCode::from(e.kind()).into();
-            }
-            ExitCode::FAILURE
+            err.report();
+            err.exit_code()
         }
     }
 }
@@ -0,0 +1,197 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use serde_json::{Map, Value, json};
+use zopfli::Format;
+
+use crate::{cli::HashAlgorithm, hash::Hasher};
+
+/// A manifest of compressed assets for CDNs and web servers.
+///
+/// The manifest is a JSON object which maps the path of each source file to
+/// its output file, sizes, `ETag` and format.
+#[derive(Debug)]
+pub struct Manifest {
+    path: PathBuf,
+    entries: Map<String, Value>,
+}
+
+impl Manifest {
+    /// Loads the manifest at `path`, so that it is updated rather than
+    /// overwritten.
+    ///
+    /// If `path` does not exist, the manifest is empty.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let entries = match fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Map::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Returns the path of the manifest.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds the entry for `source` which was compressed to `output`.
+    ///
+    /// The `ETag` is the SHA-256 digest of `output`. If `digest` is not
+    /// specified, it is computed by reading `output`.
+    pub fn insert(
+        &mut self,
+        source: &Path,
+        output: &Path,
+        original_size: u64,
+        compressed_size: u64,
+        format: Format,
+        digest: Option<&[u8]>,
+    ) -> io::Result<()> {
+        let digest = if let Some(digest) = digest {
+            digest.to_vec()
+        } else {
+            let mut hasher = Hasher::new(HashAlgorithm::Sha256);
+            io::copy(&mut File::open(output)?, &mut hasher)?;
+            hasher.finalize()
+        };
+        let mut etag = String::from("\"");
+        for b in digest {
+            write!(etag, "{b:02x}").expect("writing to a `String` should not fail");
+        }
+        etag.push('"');
+        let (format, content_encoding) = match format {
+            Format::Gzip => ("gzip", Some("gzip")),
+            // The "deflate" content coding is the zlib format.
+            Format::Zlib => ("zlib", Some("deflate")),
+            Format::Deflate => ("deflate", None),
+        };
+        self.entries.insert(
+            source.to_string_lossy().into(),
+            json!({
+                "output": output.to_string_lossy(),
+                "original_size": original_size,
+                "compressed_size": compressed_size,
+                "etag": etag,
+                "format": format,
+                "content_encoding": content_encoding,
+            }),
+        );
+        Ok(())
+    }
+
+    /// Removes the entry for `source`.
+    pub fn remove(&mut self, source: &Path) {
+        self.entries.shift_remove(source.to_string_lossy().as_ref());
+    }
+
+    /// Writes the manifest atomically.
+    ///
+    /// The manifest is written to a temporary file in the same directory
+    /// first, so that readers never see an incomplete manifest.
+    pub fn save(&mut self) -> io::Result<()> {
+        self.entries.sort_keys();
+        let mut data = serde_json::to_vec_pretty(&self.entries)?;
+        data.push(b'\n');
+        let mut temp_name = self.path.file_name().unwrap_or_default().to_owned();
+        temp_name.push(format!(".{}.tmp", process::id()));
+        let temp_path = self.path.with_file_name(temp_name);
+        let result = File::create(&temp_path)
+            .and_then(|mut f| f.write_all(&data).and_then(|()| f.sync_all()))
+            .and_then(|()| fs::rename(&temp_path, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_merge() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("manifest.json");
+        let output = temp_dir.path().join("foo.txt.gz");
+        fs::write(&output, b"foo").unwrap();
+
+        let mut manifest = Manifest::load(&path).unwrap();
+        manifest
+            .insert(Path::new("foo.txt"), &output, 10, 3, Format::Gzip, None)
+            .unwrap();
+        manifest
+            .insert(Path::new("bar.txt"), &output, 20, 3, Format::Zlib, None)
+            .unwrap();
+        manifest.save().unwrap();
+
+        let mut manifest = Manifest::load(&path).unwrap();
+        manifest.remove(Path::new("foo.txt"));
+        manifest
+            .insert(Path::new("baz.txt"), &output, 30, 3, Format::Deflate, None)
+            .unwrap();
+        manifest.save().unwrap();
+
+        let entries: Map<String, Value> =
+            serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(entries.keys().collect::<Vec<_>>(), ["bar.txt", "baz.txt"]);
+        assert_eq!(
+            entries["bar.txt"],
+            json!({
+                "output": output.to_string_lossy(),
+                "original_size": 20,
+                "compressed_size": 3,
+                "etag": "\"2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae\"",
+                "format": "zlib",
+                "content_encoding": "deflate",
+            })
+        );
+        assert_eq!(entries["baz.txt"]["content_encoding"], Value::Null);
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn insert_with_digest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut manifest = Manifest::load(&temp_dir.path().join("manifest.json")).unwrap();
+        // The output file is not read, so it does not need to exist.
+        manifest
+            .insert(
+                Path::new("foo.txt"),
+                &temp_dir.path().join("foo.txt.gz"),
+                10,
+                3,
+                Format::Gzip,
+                Some(&[0x01, 0xab]),
+            )
+            .unwrap();
+        assert_eq!(manifest.entries["foo.txt"]["etag"], "\"01ab\"");
+    }
+
+    #[test]
+    fn load_invalid_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("manifest.json");
+        for data in ["[]", "{"] {
+            fs::write(&path, data).unwrap();
+            assert_eq!(
+                Manifest::load(&path).unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+    }
+}
@@ -5,6 +5,7 @@
 use std::{
     fs::File,
     io::{self, Stdout, Write},
+    path::Path,
 };
 
 #[derive(Debug)]
@@ -28,3 +29,24 @@ impl Write for Output {
         }
     }
 }
+
+/// Synchronizes the directory containing `path` to disk, so that the creation
+/// or removal of `path` is durable.
+#[cfg(unix)]
+pub fn sync_parent(path: &Path) -> io::Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    File::open(dir)?.sync_all()
+}
+
+/// Synchronizes the directory containing `path` to disk, so that the creation
+/// or removal of `path` is durable.
+///
+/// Directories cannot be synchronized on non-Unix platforms, so this does
+/// nothing.
+#[cfg(not(unix))]
+pub fn sync_parent(_: &Path) -> io::Result<()> {
+    Ok(())
+}
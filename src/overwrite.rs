@@ -0,0 +1,171 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    ffi::OsString,
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use crate::cli::Backup;
+
+/// Asks whether to overwrite the existing file at `path`, and returns the
+/// answer.
+///
+/// The answer is "no" without asking if standard input is not a terminal.
+pub fn ask(path: &Path) -> io::Result<bool> {
+    // Prompts from multiple threads must not be interleaved.
+    static PROMPT: Mutex<()> = Mutex::new(());
+
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return Ok(false);
+    }
+    let _guard = PROMPT.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut stderr = io::stderr().lock();
+    write!(
+        stderr,
+        "{} already exists; overwrite (y or n)? ",
+        path.display()
+    )?;
+    stderr.flush()?;
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer)?;
+    Ok(answer.trim_start().starts_with(['y', 'Y']))
+}
+
+/// Returns the path of the backup file for the existing file at `path`.
+///
+/// Like `cp --backup`, a simple backup has "~" appended to the filename, and a
+/// numbered backup has ".~N~" appended, where N is one greater than the
+/// largest number of the existing backups.
+pub fn backup_path(path: &Path, method: Backup) -> io::Result<PathBuf> {
+    let latest = latest_backup_number(path)?;
+    let is_numbered = match method {
+        Backup::Simple => false,
+        Backup::Numbered => true,
+        Backup::Existing => latest > 0,
+    };
+    let mut backup = path.as_os_str().to_owned();
+    if is_numbered {
+        backup.push(format!(".~{}~", latest + 1));
+    } else {
+        backup.push("~");
+    }
+    Ok(backup.into())
+}
+
+/// Returns the largest number of the numbered backups of `path`, or 0 if
+/// there are no numbered backups.
+fn latest_backup_number(path: &Path) -> io::Result<u64> {
+    let Some(name) = path.file_name() else {
+        return Ok(0);
+    };
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let mut prefix = name.to_owned();
+    prefix.push(".~");
+    let mut latest = 0;
+    for entry in dir.read_dir()? {
+        let entry_name = entry?.file_name();
+        let number = entry_name
+            .as_encoded_bytes()
+            .strip_prefix(prefix.as_encoded_bytes())
+            .and_then(|rest| rest.strip_suffix(b"~"))
+            .and_then(|number| std::str::from_utf8(number).ok())
+            .and_then(|number| number.parse().ok());
+        if let Some(number) = number {
+            latest = latest.max(number);
+        }
+    }
+    Ok(latest)
+}
+
+/// Returns `path` with ".N" inserted before its extension.
+///
+/// For example, "foo.txt.gz" becomes "foo.txt.1.gz", so that the extension is
+/// kept.
+pub fn numbered_path(path: &Path, n: u64) -> PathBuf {
+    let mut name = path.file_stem().map(OsString::from).unwrap_or_default();
+    name.push(format!(".{n}"));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn simple_backup_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("foo.txt.gz");
+        fs::write(temp_dir.path().join("foo.txt.gz.~1~"), "").unwrap();
+        assert_eq!(
+            backup_path(&path, Backup::Simple).unwrap(),
+            temp_dir.path().join("foo.txt.gz~")
+        );
+    }
+
+    #[test]
+    fn numbered_backup_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("foo.txt.gz");
+        assert_eq!(
+            backup_path(&path, Backup::Numbered).unwrap(),
+            temp_dir.path().join("foo.txt.gz.~1~")
+        );
+        fs::write(temp_dir.path().join("foo.txt.gz.~1~"), "").unwrap();
+        fs::write(temp_dir.path().join("foo.txt.gz.~9~"), "").unwrap();
+        fs::write(temp_dir.path().join("foo.txt.gz.~a~"), "").unwrap();
+        assert_eq!(
+            backup_path(&path, Backup::Numbered).unwrap(),
+            temp_dir.path().join("foo.txt.gz.~10~")
+        );
+    }
+
+    #[test]
+    fn existing_backup_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("foo.txt.gz");
+        assert_eq!(
+            backup_path(&path, Backup::Existing).unwrap(),
+            temp_dir.path().join("foo.txt.gz~")
+        );
+        fs::write(temp_dir.path().join("foo.txt.gz.~2~"), "").unwrap();
+        assert_eq!(
+            backup_path(&path, Backup::Existing).unwrap(),
+            temp_dir.path().join("foo.txt.gz.~3~")
+        );
+    }
+
+    #[test]
+    fn numbered_path_with_extension() {
+        assert_eq!(
+            numbered_path(Path::new("foo/bar.txt.gz"), 1),
+            Path::new("foo/bar.txt.1.gz")
+        );
+        assert_eq!(
+            numbered_path(Path::new("bar.txt"), 2),
+            Path::new("bar.2.txt")
+        );
+    }
+
+    #[test]
+    fn numbered_path_without_extension() {
+        assert_eq!(numbered_path(Path::new("foo"), 1), Path::new("foo.1"));
+        assert_eq!(
+            numbered_path(Path::new(".bashrc"), 1),
+            Path::new(".bashrc.1")
+        );
+    }
+}
@@ -0,0 +1,52 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::io;
+
+use crate::cli::IoClass;
+
+/// Sets the niceness of the calling thread to `niceness`.
+///
+/// On Linux, the niceness is a per-thread attribute, so this should be called
+/// before spawning threads which inherit it.
+#[cfg(unix)]
+pub fn set_niceness(niceness: i32) -> io::Result<()> {
+    rustix::process::setpriority_process(None, niceness).map_err(io::Error::from)
+}
+
+/// Sets the niceness of the calling thread to `niceness`.
+///
+/// This is not supported on non-Unix platforms.
+#[cfg(not(unix))]
+pub fn set_niceness(_: i32) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Sets the I/O scheduling class of the calling thread to `class`.
+///
+/// Like the niceness, the I/O priority is inherited by threads spawned after
+/// this is called.
+#[cfg(target_os = "linux")]
+pub fn set_io_class(class: IoClass) -> io::Result<()> {
+    use ioprio::{BePriorityLevel, Class, Pid, Priority, Target};
+
+    let class = match class {
+        IoClass::Idle => Class::Idle,
+        IoClass::BestEffort => Class::BestEffort(BePriorityLevel::fallback()),
+    };
+    ioprio::set_priority(Target::Process(Pid::from_raw(0)), Priority::new(class)).map_err(|err| {
+        err.as_errno().map_or_else(
+            || io::Error::other(err),
+            |errno| io::Error::from_raw_os_error(errno as i32),
+        )
+    })
+}
+
+/// Sets the I/O scheduling class of the calling thread to `class`.
+///
+/// This is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn set_io_class(_: IoClass) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
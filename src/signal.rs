@@ -0,0 +1,116 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+use crate::jobserver;
+
+/// Output files which are being written.
+///
+/// These are removed if the program is terminated by a signal.
+static PARTIAL_OUTPUTS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// The jobserver whose tokens are given back if the program is terminated by a
+/// signal.
+static JOBSERVER: OnceLock<Arc<jobserver::Client>> = OnceLock::new();
+
+/// Locks [`PARTIAL_OUTPUTS`].
+fn partial_outputs() -> MutexGuard<'static, Vec<PathBuf>> {
+    PARTIAL_OUTPUTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Installs handlers for `SIGINT` and `SIGTERM`.
+///
+/// When one of these signals is received, the output files which are being
+/// written are removed, the tokens held from the jobserver are given back, and
+/// the program exits with 128 plus the signal number.
+#[cfg(unix)]
+#[allow(clippy::significant_drop_tightening)]
+pub fn install() -> std::io::Result<()> {
+    use signal_hook::{
+        consts::{SIGINT, SIGTERM},
+        iterator::Signals,
+    };
+
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            // The lock is held until the program exits, so no other thread can
+            // finish an output file after this point. An input file is only
+            // removed after its output file is finished, and such an output
+            // file is kept.
+            let outputs = partial_outputs();
+            for path in outputs.iter() {
+                let _ = fs::remove_file(path);
+            }
+            // Otherwise, make loses the job slots for the rest of the build.
+            if let Some(client) = JOBSERVER.get() {
+                let _ = client.release_all();
+            }
+            std::process::exit(128 + signal);
+        }
+    });
+    Ok(())
+}
+
+/// Installs handlers for `SIGINT` and `SIGTERM`.
+///
+/// This does nothing on non-Unix platforms.
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+pub const fn install() -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Gives back the tokens held from `client` if the program is terminated by a
+/// signal.
+pub fn release_tokens_of(client: Arc<jobserver::Client>) {
+    let _ = JOBSERVER.set(client);
+}
+
+/// A guard which marks an output file as incomplete while it is alive.
+///
+/// If the program is terminated by a signal while the guard is alive, the
+/// output file is removed.
+#[derive(Debug)]
+pub struct PartialOutput(PathBuf);
+
+impl PartialOutput {
+    /// Marks the output file at `path` as incomplete.
+    pub fn new(path: &Path) -> Self {
+        let path = path.to_path_buf();
+        partial_outputs().push(path.clone());
+        Self(path)
+    }
+}
+
+impl Drop for PartialOutput {
+    fn drop(&mut self) {
+        let mut outputs = partial_outputs();
+        if let Some(i) = outputs.iter().position(|p| p == &self.0) {
+            outputs.swap_remove(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_output() {
+        let path = Path::new("foo.txt.gz");
+        {
+            let _guard = PartialOutput::new(path);
+            assert!(partial_outputs().iter().any(|p| p == path));
+        }
+        assert!(!partial_outputs().iter().any(|p| p == path));
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The shortest pause, to avoid sleeping after each small write.
+const MIN_PAUSE: Duration = Duration::from_millis(10);
+
+/// A writer which limits the CPU time used by the calling thread.
+///
+/// Zopfli writes the compressed data after compressing each block, so the time
+/// spent between writes is regarded as busy time. Before each write, the
+/// thread sleeps so that the busy time is at most `percent` of the total
+/// time.
+#[derive(Debug)]
+pub struct Throttle<W> {
+    inner: W,
+    percent: Option<u8>,
+    last: Instant,
+    debt: Duration,
+}
+
+impl<W> Throttle<W> {
+    /// Creates a new `Throttle`.
+    ///
+    /// If `percent` is [`None`], writes are not throttled.
+    pub fn new(inner: W, percent: Option<u8>) -> Self {
+        Self {
+            inner,
+            percent,
+            last: Instant::now(),
+            debt: Duration::ZERO,
+        }
+    }
+}
+
+impl<W: Write> Write for Throttle<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(percent) = self.percent {
+            self.debt += pause(self.last.elapsed(), percent);
+            if self.debt >= MIN_PAUSE {
+                thread::sleep(self.debt);
+                self.debt = Duration::ZERO;
+            }
+        }
+        let n = self.inner.write(buf)?;
+        self.last = Instant::now();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns how long to pause after being busy for `busy` so that the busy time
+/// is `percent` of the total time.
+fn pause(busy: Duration, percent: u8) -> Duration {
+    let percent = u32::from(percent.clamp(1, 100));
+    busy * (100 - percent) / percent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_with_percent() {
+        let busy = Duration::from_millis(100);
+        assert_eq!(pause(busy, 100), Duration::ZERO);
+        assert_eq!(pause(busy, 50), busy);
+        assert_eq!(pause(busy, 25), Duration::from_millis(300));
+        assert_eq!(pause(busy, 1), Duration::from_millis(9900));
+    }
+
+    #[test]
+    fn throttle_without_percent() {
+        let mut writer = Throttle::new(Vec::new(), None);
+        writer.write_all(b"Hello, world!").unwrap();
+        assert_eq!(writer.inner, b"Hello, world!");
+    }
+}
@@ -0,0 +1,56 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Returns the paths of regular files in `dir` and its subdirectories.
+///
+/// Symbolic links are not followed. The paths are sorted.
+pub fn files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in dir.read_dir()? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                walk(&entry.path(), files)?;
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(dir, &mut files)?;
+    files.sort_unstable();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn files_in_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_dir_path = temp_dir.path();
+        fs::create_dir_all(temp_dir_path.join("foo/bar")).unwrap();
+        fs::write(temp_dir_path.join("foo/bar/baz.txt"), "").unwrap();
+        fs::write(temp_dir_path.join("foo/qux.txt"), "").unwrap();
+        fs::write(temp_dir_path.join("quux.txt"), "").unwrap();
+        assert_eq!(
+            files(temp_dir_path).unwrap(),
+            [
+                temp_dir_path.join("foo/bar/baz.txt"),
+                temp_dir_path.join("foo/qux.txt"),
+                temp_dir_path.join("quux.txt")
+            ]
+        );
+    }
+}
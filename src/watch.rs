@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A change of a file in a watched directory.
+#[derive(Debug)]
+pub enum Change {
+    /// The file already exists when the directory starts to be watched.
+    Found(PathBuf),
+
+    /// The file was closed after writing, or moved into the directory.
+    Written(PathBuf),
+
+    /// The file was removed, or moved out of the directory.
+    Removed(PathBuf),
+}
+
+/// Watches `dir` and its subdirectories, and calls `f` for each change of
+/// regular files.
+///
+/// `f` is first called with [`Change::Found`] for each existing file. This
+/// does not return unless an error occurs.
+#[cfg(target_os = "linux")]
+pub fn watch(dir: &Path, mut f: impl FnMut(Change)) -> io::Result<()> {
+    use std::collections::HashMap;
+
+    use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+    use log::warn;
+
+    /// Adds a watch for `dir` and its subdirectories, and calls `f` for each
+    /// existing file.
+    fn add(
+        inotify: &Inotify,
+        dirs: &mut HashMap<WatchDescriptor, PathBuf>,
+        dir: &Path,
+        f: &mut impl FnMut(Change),
+    ) -> io::Result<()> {
+        let mask = WatchMask::CLOSE_WRITE
+            | WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::ONLYDIR
+            | WatchMask::DONT_FOLLOW;
+        let wd = inotify.watches().add(dir, mask)?;
+        dirs.insert(wd, dir.to_path_buf());
+        // Files which are created before the watch is added are found here.
+        for entry in dir.read_dir()? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                add(inotify, dirs, &entry.path(), f)?;
+            } else if file_type.is_file() {
+                f(Change::Found(entry.path()));
+            }
+        }
+        Ok(())
+    }
+
+    let mut inotify = Inotify::init()?;
+    let mut dirs = HashMap::new();
+    add(&inotify, &mut dirs, dir, &mut f)?;
+
+    let mut buf = [u8::default(); 4096];
+    loop {
+        let events = inotify
+            .read_events_blocking(&mut buf)?
+            .map(|event| event.to_owned())
+            .collect::<Vec<_>>();
+        for event in events {
+            if event.mask.contains(EventMask::Q_OVERFLOW) {
+                // Adding a watch for a directory which is already watched
+                // returns the same watch descriptor, so this only finds files.
+                warn!("some changes could not be watched, so all files are checked again");
+                add(&inotify, &mut dirs, dir, &mut f)?;
+                continue;
+            }
+            if event.mask.contains(EventMask::IGNORED) {
+                dirs.remove(&event.wd);
+                continue;
+            }
+            let (Some(parent), Some(name)) = (dirs.get(&event.wd), event.name) else {
+                continue;
+            };
+            let path = parent.join(name);
+            if event.mask.contains(EventMask::ISDIR) {
+                if event
+                    .mask
+                    .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                {
+                    // The directory may already be removed.
+                    if let Err(err) = add(&inotify, &mut dirs, &path, &mut f) {
+                        warn!("could not watch {}: {err}", path.display());
+                    }
+                }
+            } else if event
+                .mask
+                .intersects(EventMask::CLOSE_WRITE | EventMask::MOVED_TO)
+            {
+                f(Change::Written(path));
+            } else if event
+                .mask
+                .intersects(EventMask::DELETE | EventMask::MOVED_FROM)
+            {
+                f(Change::Removed(path));
+            }
+        }
+    }
+}
+
+/// Watches `dir` and its subdirectories, and calls `f` for each change of
+/// regular files.
+///
+/// This is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn watch(_: &Path, _: impl FnMut(Change)) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
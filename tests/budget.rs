@@ -0,0 +1,165 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_within_budget() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let input_filename = temp_dir.path().join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--budget")
+        .arg("foo.txt.gz=1 MiB")
+        .arg("--budget")
+        .arg("*.gz=50%")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert!(temp_dir.path().join("foo.txt.gz").exists());
+}
+
+#[test]
+fn compress_exceeding_budget() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt", "baz.txt"].map(|f| temp_dir_path.join(f));
+    for (i, input_filename) in input_filenames.iter().enumerate() {
+        fs::write(
+            input_filename,
+            [TEST_DATA, &[b'0' + u8::try_from(i).unwrap()]].concat(),
+        )
+        .unwrap();
+    }
+    utils::command::command()
+        .arg("--budget")
+        .arg("foo.txt.gz=1 KiB")
+        .arg("--budget")
+        .arg("ba[rz].txt.gz=1%")
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Error: 3 files exceeded their size budgets",
+        ))
+        .stderr(predicate::str::is_match(r"FILE +SIZE +RATIO +BUDGET\n").unwrap())
+        .stderr(
+            predicate::str::is_match(r"foo\.txt\.gz +[0-9.]+ KiB +[0-9.]+% +foo\.txt\.gz=1 KiB\n")
+                .unwrap(),
+        )
+        .stderr(predicate::str::is_match(r"bar\.txt\.gz .* ba\[rz\]\.txt\.gz=1%\n").unwrap())
+        .stderr(predicate::str::is_match(r"baz\.txt\.gz .* ba\[rz\]\.txt\.gz=1%\n").unwrap());
+    // The output files are kept.
+    for input_filename in input_filenames {
+        let mut output_filename = input_filename.into_os_string();
+        output_filename.push(".gz");
+        assert!(fs::exists(output_filename).unwrap());
+    }
+}
+
+#[test]
+fn compress_with_budget_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let budget_file = temp_dir_path.join("budgets");
+    fs::write(&budget_file, "# Budgets\n\nfoo.txt.gz = 1 KiB\n").unwrap();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--budget-file")
+        .arg(&budget_file)
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Error: 1 files exceeded their size budgets",
+        ))
+        .stderr(predicate::str::contains("foo.txt.gz = 1 KiB"));
+}
+
+#[test]
+fn compress_with_invalid_budget_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let budget_file = temp_dir_path.join("budgets");
+    fs::write(&budget_file, "foo.txt.gz=1 KiB\nfoo.txt.gz\n").unwrap();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--budget-file")
+        .arg(&budget_file)
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .code(65)
+        .stderr(predicate::str::contains(format!(
+            "could not read the budgets from {}",
+            budget_file.display()
+        )))
+        .stderr(predicate::str::contains("line 2: "));
+    assert!(!temp_dir_path.join("foo.txt.gz").exists());
+}
+
+#[test]
+fn compress_with_invalid_budget() {
+    utils::command::command()
+        .arg("--budget")
+        .arg("foo.txt.gz")
+        .arg("data/LICENSES/CC-BY-4.0.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'foo.txt.gz' for '--budget <BUDGET>'",
+        ));
+}
+
+#[test]
+fn precompress_up_to_date_files_exceeding_budget() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("app.js"), TEST_DATA).unwrap();
+    for _ in 0..2 {
+        utils::command::command()
+            .arg("--static")
+            .arg(root)
+            .arg("--budget")
+            .arg("app.js.gz=1 KiB")
+            .arg("-i")
+            .arg("1")
+            .assert()
+            .failure()
+            .code(3)
+            .stderr(predicate::str::contains("app.js.gz=1 KiB"));
+    }
+}
+
+#[test]
+fn budget_conflicts_with_stdout() {
+    utils::command::command()
+        .arg("--budget")
+        .arg("*=1 KiB")
+        .arg("-c")
+        .arg("data/LICENSES/CC-BY-4.0.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the argument '--budget <BUDGET>' cannot be used with '--stdout'",
+        ));
+}
@@ -0,0 +1,159 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{fs, io::Read};
+
+use flate2::read::GzDecoder;
+use predicates::prelude::{PredicateBooleanExt, predicate};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_with_cache() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let cache_dir = temp_dir_path.join("cache");
+    for input_filename in ["foo.txt", "bar.txt"] {
+        fs::write(temp_dir_path.join(input_filename), TEST_DATA).unwrap();
+    }
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg(temp_dir_path.join("foo.txt"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Reusing the cached compressed data").not());
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg(temp_dir_path.join("bar.txt"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Reusing the cached compressed data",
+        ));
+    let compressed_data = fs::read(temp_dir_path.join("bar.txt.gz")).unwrap();
+    assert_eq!(
+        compressed_data,
+        fs::read(temp_dir_path.join("foo.txt.gz")).unwrap()
+    );
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = Vec::new();
+    decoder.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+
+    // Different options do not share the cached data.
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg("-i")
+        .arg("1")
+        .arg("-c")
+        .arg(temp_dir_path.join("foo.txt"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Reusing the cached compressed data").not());
+}
+
+#[test]
+fn cache_stats_and_clear() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let cache_dir = temp_dir_path.join("cache");
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    utils::command::command()
+        .arg("--cache-stats")
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Entries: 1\n"));
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg("--cache-clear")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "1 entries have been removed from the cache",
+        ));
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg("--cache-stats")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Entries: 0\n"));
+}
+
+#[test]
+fn compress_with_cache_size() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let cache_dir = temp_dir_path.join("cache");
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg("--cache-size")
+        .arg("1 KiB")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "1 entries have been evicted from the cache",
+        ));
+    utils::command::command()
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg("--cache-stats")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Entries: 0\n"));
+}
+
+#[test]
+fn compress_file_named_cache() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    for name in ["cache", "help"] {
+        fs::write(temp_dir_path.join(name), TEST_DATA).unwrap();
+    }
+    utils::command::command()
+        .current_dir(temp_dir_path)
+        .arg("-i")
+        .arg("1")
+        .arg("cache")
+        .arg("help")
+        .assert()
+        .success();
+    assert!(temp_dir_path.join("cache.gz").exists());
+    assert!(temp_dir_path.join("help.gz").exists());
+}
+
+#[test]
+fn cache_without_cache_dir() {
+    utils::command::command()
+        .arg("--cache-stats")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the following required arguments were not provided:\n  --cache-dir <DIR>",
+        ));
+}
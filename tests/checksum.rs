@@ -0,0 +1,102 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+use sha2::{Digest, Sha256};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_with_checksum_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let mut output_filename = input_filename.clone();
+    output_filename.as_mut_os_string().push(".gz");
+    let checksum_filename = temp_dir_path.join("SHA256SUMS");
+    utils::command::command()
+        .arg("--checksum-file")
+        .arg(&checksum_filename)
+        .arg(&input_filename)
+        .assert()
+        .success();
+    let compressed_data = fs::read(&output_filename).unwrap();
+    assert_eq!(
+        fs::read_to_string(checksum_filename).unwrap(),
+        format!(
+            "{:x}  {}\n",
+            Sha256::digest(compressed_data),
+            output_filename.display()
+        )
+    );
+}
+
+#[test]
+fn compress_with_checksum_input() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let mut output_filename = input_filename.clone();
+    output_filename.as_mut_os_string().push(".gz");
+    let checksum_filename = temp_dir_path.join("SHA256SUMS");
+    utils::command::command()
+        .arg("--checksum-file")
+        .arg(&checksum_filename)
+        .arg("--checksum-input")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    let compressed_data = fs::read(&output_filename).unwrap();
+    assert_eq!(
+        fs::read_to_string(checksum_filename).unwrap(),
+        format!(
+            "{:x}  {}\n{:x}  {}\n",
+            Sha256::digest(TEST_DATA),
+            input_filename.display(),
+            Sha256::digest(compressed_data),
+            output_filename.display()
+        )
+    );
+}
+
+#[test]
+fn compress_with_blake3_checksum() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let checksum_filename = temp_dir_path.join("B3SUMS");
+    let output = utils::command::command()
+        .arg("-c")
+        .arg("--checksum-file")
+        .arg(&checksum_filename)
+        .arg("--checksum-algorithm")
+        .arg("blake3")
+        .write_stdin(TEST_DATA)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(checksum_filename).unwrap(),
+        format!("{}  -\n", blake3::hash(&output.stdout).to_hex())
+    );
+}
+
+#[test]
+fn checksum_input_requires_checksum_file() {
+    utils::command::command()
+        .arg("--checksum-input")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the following required arguments were not provided",
+        ));
+}
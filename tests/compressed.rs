@@ -0,0 +1,124 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{fs, io::Write};
+
+use flate2::{Compression, write::ZlibEncoder};
+use predicates::prelude::{PredicateBooleanExt, predicate};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn skip_file_with_suffix() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt.gz");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} already has .gz suffix, so it has been skipped",
+            input_filename.display()
+        )));
+    assert!(!temp_dir_path.join("foo.txt.gz.gz").exists());
+}
+
+#[test]
+fn skip_compressed_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.bin", "bar.png", "baz.txt"].map(|f| temp_dir_path.join(f));
+    fs::write(&input_filenames[0], utils::gzip::compress(TEST_DATA)).unwrap();
+    fs::write(&input_filenames[1], b"\x89PNG\r\n\x1a\n").unwrap();
+    fs::write(&input_filenames[2], TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} already contains gzip data, so it has been skipped",
+            input_filenames[0].display()
+        )))
+        .stderr(predicate::str::contains(format!(
+            "{} already contains PNG data, so it has been skipped",
+            input_filenames[1].display()
+        )))
+        .stderr(predicate::str::contains(
+            "1 files have been compressed, 2 files have been skipped",
+        ));
+    assert!(!temp_dir_path.join("foo.bin.gz").exists());
+    assert!(!temp_dir_path.join("bar.png.gz").exists());
+    assert!(temp_dir_path.join("baz.txt.gz").exists());
+}
+
+#[test]
+fn skip_zlib_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.zz");
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(TEST_DATA).unwrap();
+    fs::write(&input_filename, encoder.finish().unwrap()).unwrap();
+    utils::command::command()
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} already contains zlib data, so it has been skipped",
+            input_filename.display()
+        )));
+    assert!(!temp_dir_path.join("foo.zz.gz").exists());
+}
+
+#[test]
+fn compress_text_like_zlib_header() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["data.csv", "script.txt"].map(|f| temp_dir_path.join(f));
+    fs::write(&input_filenames[0], [b"80,120\n", TEST_DATA].concat()).unwrap();
+    fs::write(&input_filenames[1], [b"x = 1\n", TEST_DATA].concat()).unwrap();
+    utils::command::command()
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("already contains").not())
+        .stderr(predicate::str::contains(
+            "2 files have been compressed, 0 files have been skipped",
+        ));
+    assert!(temp_dir_path.join("data.csv.gz").exists());
+    assert!(temp_dir_path.join("script.txt.gz").exists());
+}
+
+#[test]
+fn compress_compressed_file_with_force() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.gz");
+    fs::write(&input_filename, utils::gzip::compress(TEST_DATA)).unwrap();
+    utils::command::command()
+        .arg("-f")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert!(temp_dir_path.join("foo.gz.gz").exists());
+}
+
+#[test]
+fn list_compressed_types() {
+    utils::command::command()
+        .arg("--list-compressed-types")
+        .assert()
+        .success()
+        .stdout("gzip\nzlib\nzstd\nxz\nbzip2\nPNG\nJPEG\n");
+}
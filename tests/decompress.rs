@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use flate2::{Compression, write::ZlibEncoder};
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+/// Copies the binary into `dir` as `name`.
+fn install_as(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(format!("{name}{}", env::consts::EXE_SUFFIX));
+    fs::copy(env!("CARGO_BIN_EXE_rzopfli"), &path).unwrap();
+    path
+}
+
+#[test]
+fn decompress_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt.gz");
+    fs::write(&input_filename, utils::gzip::compress(TEST_DATA)).unwrap();
+    utils::command::command()
+        .arg("-d")
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Decompressed:"));
+    assert_eq!(fs::read(temp_dir_path.join("foo.txt")).unwrap(), TEST_DATA);
+    assert!(input_filename.exists());
+}
+
+#[test]
+fn decompress_file_with_remove() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt.gz");
+    fs::write(&input_filename, utils::gzip::compress(TEST_DATA)).unwrap();
+    utils::command::command()
+        .arg("--decompress")
+        .arg("--rm")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert_eq!(fs::read(temp_dir_path.join("foo.txt")).unwrap(), TEST_DATA);
+    assert!(!input_filename.exists());
+}
+
+#[test]
+fn decompress_from_stdin() {
+    utils::command::command()
+        .arg("-d")
+        .write_stdin(utils::gzip::compress(TEST_DATA))
+        .assert()
+        .success()
+        .stdout(TEST_DATA);
+}
+
+#[test]
+fn decompress_zlib() {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("-d")
+        .arg("--format")
+        .arg("zlib")
+        .write_stdin(encoder.finish().unwrap())
+        .assert()
+        .success()
+        .stdout(TEST_DATA);
+}
+
+#[test]
+fn decompress_invalid_data() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt.gz");
+    fs::write(&input_filename, &utils::gzip::compress(TEST_DATA)[..100]).unwrap();
+    utils::command::command()
+        .arg("-d")
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .code(65)
+        .stderr(predicate::str::contains("could not be decompressed"));
+    assert!(!temp_dir_path.join("foo.txt").exists());
+}
+
+#[test]
+fn decompress_file_without_suffix() {
+    utils::command::command()
+        .arg("-d")
+        .arg("data/LICENSES/CC-BY-4.0.txt")
+        .assert()
+        .failure()
+        .code(64)
+        .stderr(predicate::str::contains("does not have the suffix .gz"));
+}
+
+#[test]
+fn invoke_as_unzopfli() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt.gz");
+    fs::write(&input_filename, utils::gzip::compress(TEST_DATA)).unwrap();
+    assert_cmd::Command::new(install_as(temp_dir_path, "unzopfli"))
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert_eq!(fs::read(temp_dir_path.join("foo.txt")).unwrap(), TEST_DATA);
+}
+
+#[test]
+fn invoke_as_runzopfli() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    assert_cmd::Command::new(install_as(temp_dir.path(), "runzopfli"))
+        .arg("-d")
+        .write_stdin(utils::gzip::compress(TEST_DATA))
+        .assert()
+        .success()
+        .stdout(TEST_DATA);
+}
+
+#[test]
+fn invoke_as_zopfcat() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt.gz");
+    fs::write(&input_filename, utils::gzip::compress(TEST_DATA)).unwrap();
+    assert_cmd::Command::new(install_as(temp_dir_path, "zopfcat"))
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stdout(TEST_DATA);
+    assert!(!temp_dir_path.join("foo.txt").exists());
+}
@@ -0,0 +1,128 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::{PredicateBooleanExt, predicate};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_identical_files_once() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt", "baz.txt"].map(|f| temp_dir_path.join(f));
+    fs::write(&input_filenames[0], TEST_DATA).unwrap();
+    fs::write(&input_filenames[1], &TEST_DATA[..1024]).unwrap();
+    fs::write(&input_filenames[2], TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Reusing the compressed data of {}, which is identical",
+            input_filenames[0].display()
+        )))
+        .stderr(
+            predicate::str::contains(format!(
+                "Reusing the compressed data of {}",
+                input_filenames[1].display()
+            ))
+            .not(),
+        )
+        .stderr(predicate::str::contains("3 files have been compressed"));
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.gz")),
+        TEST_DATA
+    );
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("bar.txt.gz")),
+        &TEST_DATA[..1024]
+    );
+    assert_eq!(
+        fs::read(temp_dir_path.join("baz.txt.gz")).unwrap(),
+        fs::read(temp_dir_path.join("foo.txt.gz")).unwrap()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn compress_hard_links_once() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    fs::write(&input_filenames[0], TEST_DATA).unwrap();
+    fs::hard_link(&input_filenames[0], &input_filenames[1]).unwrap();
+    utils::command::command()
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Reusing the compressed data of {}, which is identical",
+            input_filenames[0].display()
+        )));
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("bar.txt.gz")),
+        TEST_DATA
+    );
+}
+
+#[test]
+fn compress_identical_files_with_checksum_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, TEST_DATA).unwrap();
+    }
+    let checksum_file = temp_dir_path.join("SHA256SUMS");
+    utils::command::command()
+        .arg("-i")
+        .arg("1")
+        .arg("--checksum-file")
+        .arg(&checksum_file)
+        .arg("--checksum-input")
+        .args(&input_filenames)
+        .assert()
+        .success();
+    let checksums = fs::read_to_string(checksum_file).unwrap();
+    let digests = checksums
+        .lines()
+        .map(|line| line.split_once(' ').unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(digests.len(), 4);
+    assert_eq!(digests[0], digests[2]);
+    assert_eq!(digests[1], digests[3]);
+    assert_ne!(digests[0], digests[1]);
+}
+
+#[test]
+fn precompress_identical_files_once() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    fs::create_dir_all(temp_dir_path.join("foo")).unwrap();
+    fs::write(temp_dir_path.join("foo/bar.js"), TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("baz.js"), TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--static")
+        .arg(temp_dir_path)
+        .arg("-i")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Reusing the compressed data of {}, which is identical",
+            temp_dir_path.join("baz.js").display()
+        )));
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo/bar.js.gz")),
+        TEST_DATA
+    );
+}
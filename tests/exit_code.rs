@@ -0,0 +1,66 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_non_existent_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let input_filename = temp_dir.path().join("foo.txt");
+    utils::command::command()
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .code(66)
+        .stderr(predicate::str::contains(format!(
+            "could not open {}",
+            input_filename.display()
+        )));
+}
+
+#[test]
+fn compress_with_invalid_mirror() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    fs::create_dir(temp_dir_path.join("foo")).unwrap();
+    fs::write(temp_dir_path.join("bar.txt"), TEST_DATA).unwrap();
+    utils::command::command()
+        .current_dir(temp_dir_path.join("foo"))
+        .arg("--output-dir")
+        .arg("out")
+        .arg("--mirror")
+        .arg("../bar.txt")
+        .assert()
+        .failure()
+        .code(64);
+}
+
+#[test]
+fn compress_multiple_files_with_partial_failure() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = [temp_dir_path.join("foo.txt"), temp_dir_path.join("bar.txt")];
+    fs::write(&input_filenames[1], TEST_DATA).unwrap();
+    utils::command::command()
+        .args(&input_filenames)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(format!(
+            "could not open {}",
+            input_filenames[0].display()
+        )))
+        .stderr(predicate::str::contains(
+            "1 of 2 files could not be processed",
+        ));
+    let mut output_filename = input_filenames[1].clone();
+    output_filename.as_mut_os_string().push(".gz");
+    assert!(output_filename.exists());
+}
@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_files_from_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar baz.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, TEST_DATA).unwrap();
+    }
+    let list = temp_dir_path.join("list.txt");
+    fs::write(
+        &list,
+        format!(
+            "{}\n\n{}\n",
+            input_filenames[0].display(),
+            input_filenames[1].display()
+        ),
+    )
+    .unwrap();
+    utils::command::command()
+        .arg("--files-from")
+        .arg(&list)
+        .arg("-i")
+        .arg("1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "2 files have been compressed, 0 files have been skipped, 0 files could not be processed",
+        ));
+    assert!(temp_dir_path.join("foo.txt.gz").exists());
+    assert!(temp_dir_path.join("bar baz.txt.gz").exists());
+}
+
+#[test]
+fn compress_files_from_stdin_with_null() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar\nbaz.txt", "qux.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, TEST_DATA).unwrap();
+    }
+    let mut list = Vec::new();
+    for input_filename in &input_filenames[1..] {
+        list.extend_from_slice(input_filename.as_os_str().as_encoded_bytes());
+        list.push(b'\0');
+    }
+    utils::command::command()
+        .arg("--files-from")
+        .arg("-")
+        .arg("-0")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filenames[0])
+        .write_stdin(list)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("3 files have been compressed"));
+    assert!(temp_dir_path.join("foo.txt.gz").exists());
+    assert!(temp_dir_path.join("bar\nbaz.txt.gz").exists());
+    assert!(temp_dir_path.join("qux.txt.gz").exists());
+}
+
+#[test]
+fn compress_files_from_file_with_missing_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let list = temp_dir_path.join("list.txt");
+    fs::write(
+        &list,
+        format!(
+            "{}\n{}\n",
+            input_filename.display(),
+            temp_dir_path.join("bar.txt").display()
+        ),
+    )
+    .unwrap();
+    utils::command::command()
+        .arg("--files-from")
+        .arg(&list)
+        .arg("-i")
+        .arg("1")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "1 files have been compressed, 0 files have been skipped, 1 files could not be processed",
+        ));
+    assert!(temp_dir_path.join("foo.txt.gz").exists());
+}
+
+#[test]
+fn compress_files_from_empty_list() {
+    utils::command::command()
+        .arg("--files-from")
+        .arg("-")
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn compress_files_from_non_existent_file() {
+    utils::command::command()
+        .arg("--files-from")
+        .arg("non_existent.txt")
+        .assert()
+        .failure()
+        .code(66);
+}
+
+#[test]
+fn null_requires_files_from() {
+    utils::command::command()
+        .arg("-0")
+        .arg("data/LICENSES/CC-BY-4.0.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--files-from <PATH>"));
+}
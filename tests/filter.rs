@@ -0,0 +1,107 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_recursively() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    fs::create_dir_all(temp_dir_path.join("foo/bar")).unwrap();
+    fs::write(temp_dir_path.join("foo/baz.txt"), TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("foo/bar/qux.txt"), TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("-r")
+        .arg("-i")
+        .arg("1")
+        .arg(temp_dir_path.join("foo"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("2 files have been compressed"));
+    assert!(temp_dir_path.join("foo/baz.txt.gz").exists());
+    assert!(temp_dir_path.join("foo/bar/qux.txt.gz").exists());
+}
+
+#[test]
+fn compress_with_filters() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    fs::create_dir_all(temp_dir_path.join("public/vendor")).unwrap();
+    fs::write(temp_dir_path.join("public/index.html"), TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("public/style.css"), TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("public/logo.png"), TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("public/vendor/lib.js"), TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("public/small.js"), b"foo").unwrap();
+    fs::write(temp_dir_path.join("public/large.json"), TEST_DATA.repeat(2)).unwrap();
+    utils::command::command()
+        .arg("-r")
+        .args(["--include", "*.html", "--include", "*.css"])
+        .args(["--include", "*.js", "--include", "*.json"])
+        .args(["--exclude", "vendor/**"])
+        .args(["--min-size", "1 KiB", "--max-size", "30 KiB"])
+        .arg("-i")
+        .arg("1")
+        .arg(temp_dir_path.join("public"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "1 files have been excluded by --include",
+        ))
+        .stderr(predicate::str::contains(
+            "1 files have been excluded by --exclude",
+        ))
+        .stderr(predicate::str::contains(
+            "1 files have been excluded by --min-size",
+        ))
+        .stderr(predicate::str::contains(
+            "1 files have been excluded by --max-size",
+        ))
+        .stderr(predicate::str::contains("2 files have been compressed"));
+    assert!(temp_dir_path.join("public/index.html.gz").exists());
+    assert!(temp_dir_path.join("public/style.css.gz").exists());
+    assert!(!temp_dir_path.join("public/logo.png.gz").exists());
+    assert!(!temp_dir_path.join("public/vendor/lib.js.gz").exists());
+    assert!(!temp_dir_path.join("public/small.js.gz").exists());
+    assert!(!temp_dir_path.join("public/large.json.gz").exists());
+}
+
+#[test]
+fn compress_files_with_min_size() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    fs::write(&input_filenames[0], TEST_DATA).unwrap();
+    fs::write(&input_filenames[1], b"bar").unwrap();
+    utils::command::command()
+        .arg("--min-size")
+        .arg("1KiB")
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "1 files have been excluded by --min-size",
+        ));
+    assert!(temp_dir_path.join("foo.txt.gz").exists());
+    assert!(!temp_dir_path.join("bar.txt.gz").exists());
+}
+
+#[test]
+fn compress_with_invalid_glob() {
+    utils::command::command()
+        .arg("--include")
+        .arg("[a-z")
+        .arg("data/LICENSES/CC-BY-4.0.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("the pattern has an unclosed `[`"));
+}
@@ -0,0 +1,218 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{fs, io::Read};
+
+use assert_cmd::assert::OutputAssertExt;
+use flate2::read::MultiGzDecoder;
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+/// Returns the test data followed by `path`.
+///
+/// Each input file has different content, so that identical files are not
+/// compressed only once and they are still compressed in parallel.
+fn unique_test_data(path: &std::path::Path) -> Vec<u8> {
+    [TEST_DATA, path.as_os_str().as_encoded_bytes()].concat()
+}
+
+#[test]
+fn compress_with_jobs() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt", "baz.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, unique_test_data(input_filename)).unwrap();
+    }
+    utils::command::command()
+        .env_remove("MAKEFLAGS")
+        .arg("-j")
+        .arg("3")
+        .args(&input_filenames)
+        .assert()
+        .success();
+    for input_filename in input_filenames {
+        let mut output_filename = input_filename.clone();
+        output_filename.as_mut_os_string().push(".gz");
+        assert_eq!(
+            utils::gzip::decompress(&output_filename),
+            unique_test_data(&input_filename)
+        );
+    }
+}
+
+#[test]
+fn compress_with_jobs_and_partial_failure() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .env_remove("MAKEFLAGS")
+        .arg("-j")
+        .arg("0")
+        .arg(&input_filename)
+        .arg(temp_dir_path.join("bar.txt"))
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "1 of 2 files could not be processed",
+        ));
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.gz")),
+        TEST_DATA
+    );
+}
+
+#[test]
+fn compress_with_jobs_to_stdout() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let foo_filename = temp_dir_path.join("foo.txt");
+    fs::write(&foo_filename, TEST_DATA).unwrap();
+    let bar_filename = temp_dir_path.join("bar.txt");
+    fs::write(&bar_filename, b"Hello, world!\n").unwrap();
+    let output = utils::command::command()
+        .env_remove("MAKEFLAGS")
+        .arg("-j")
+        .arg("2")
+        .arg("-c")
+        .arg(foo_filename)
+        .arg(bar_filename)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut decoder = MultiGzDecoder::new(output.stdout.as_slice());
+    let mut buf = Vec::new();
+    decoder.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, [TEST_DATA, b"Hello, world!\n"].concat());
+}
+
+#[cfg(unix)]
+#[test]
+fn compress_with_jobserver() {
+    use std::{fs::File, io::Write, sync::mpsc, thread, time::Duration};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let fifo = temp_dir_path.join("jobserver");
+    assert!(
+        std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap()
+            .success()
+    );
+    let mut jobserver = File::options().read(true).write(true).open(&fifo).unwrap();
+    jobserver.write_all(b"+").unwrap();
+    let input_filenames = ["foo.txt", "bar.txt", "baz.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, unique_test_data(input_filename)).unwrap();
+    }
+    utils::command::command()
+        .env(
+            "MAKEFLAGS",
+            format!(" -j2 --jobserver-auth=fifo:{}", fifo.display()),
+        )
+        .arg("-j")
+        .arg("3")
+        .args(&input_filenames)
+        .assert()
+        .success();
+    for input_filename in input_filenames {
+        let mut output_filename = input_filename.clone();
+        output_filename.as_mut_os_string().push(".gz");
+        assert_eq!(
+            utils::gzip::decompress(&output_filename),
+            unique_test_data(&input_filename)
+        );
+    }
+
+    // The token should be given back.
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut token = [u8::default()];
+        jobserver.read_exact(&mut token).unwrap();
+        sender.send(token).unwrap();
+    });
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap(),
+        *b"+"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn compress_with_jobserver_fds_not_passed_on() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, unique_test_data(input_filename)).unwrap();
+    }
+    // The file descriptors refer to a regular file instead of the pipe.
+    let stdin = temp_dir_path.join("stdin");
+    fs::write(&stdin, b"foo").unwrap();
+    // `assert_cmd` always pipes standard input, so the command is run directly.
+    std::process::Command::new(env!("CARGO_BIN_EXE_rzopfli"))
+        .env("MAKEFLAGS", " -j4 --jobserver-auth=0,0")
+        .arg("-j")
+        .arg("2")
+        .args(&input_filenames)
+        .stdin(fs::File::open(&stdin).unwrap())
+        .output()
+        .unwrap()
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "could not connect to the jobserver, so files are compressed one at a time: the jobserver is not a pipe",
+        ));
+    assert_eq!(fs::read(stdin).unwrap(), b"foo");
+    for input_filename in input_filenames {
+        let mut output_filename = input_filename.clone();
+        output_filename.as_mut_os_string().push(".gz");
+        assert_eq!(
+            utils::gzip::decompress(&output_filename),
+            unique_test_data(&input_filename)
+        );
+    }
+}
+
+#[test]
+fn compress_with_unavailable_jobserver() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, unique_test_data(input_filename)).unwrap();
+    }
+    utils::command::command()
+        .env(
+            "MAKEFLAGS",
+            format!(
+                " -j2 --jobserver-auth=fifo:{}",
+                temp_dir_path.join("jobserver").display()
+            ),
+        )
+        .arg("-j")
+        .arg("2")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "could not connect to the jobserver",
+        ));
+    for input_filename in input_filenames {
+        let mut output_filename = input_filename.clone();
+        output_filename.as_mut_os_string().push(".gz");
+        assert_eq!(
+            utils::gzip::decompress(&output_filename),
+            unique_test_data(&input_filename)
+        );
+    }
+}
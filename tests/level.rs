@@ -0,0 +1,60 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+fn compress(args: &[&str]) -> Vec<u8> {
+    let output = utils::command::command()
+        .args(args)
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut decoder = GzDecoder::new(output.stdout.as_slice());
+    let mut buf = Vec::new();
+    decoder.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+    output.stdout
+}
+
+#[test]
+fn compress_with_fast() {
+    assert_eq!(compress(&["-1"]), compress(&["--fast"]));
+}
+
+#[test]
+fn compress_with_level() {
+    assert_eq!(compress(&["-9", "-3"]), compress(&["-3"]));
+}
+
+#[test]
+fn compress_with_best_and_iteration() {
+    // The number of iterations is overridden, but the number of block splits
+    // is still that of the level.
+    assert_eq!(
+        compress(&["--best", "-i", "1"]),
+        compress(&["-8", "-i", "1"])
+    );
+}
+
+#[test]
+fn compress_with_invalid_level() {
+    // "-0" is not a compression level, but the short form of "--null".
+    utils::command::command()
+        .arg("-0")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--files-from <PATH>"));
+}
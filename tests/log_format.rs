@@ -0,0 +1,197 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+use serde_json::Value;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_with_json_log_format() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let mut output_filename = input_filename.clone();
+    output_filename.as_mut_os_string().push(".gz");
+    let output = utils::command::command()
+        .arg("--log-format")
+        .arg("json")
+        .arg(&input_filename)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let records = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let record = records
+        .iter()
+        .find(|record| record["stage"] == "compress")
+        .unwrap();
+    assert_eq!(record["level"], "INFO");
+    assert_eq!(record["file"], input_filename.display().to_string());
+    assert_eq!(record["original_size"], TEST_DATA.len());
+    assert_eq!(
+        record["compressed_size"],
+        fs::metadata(output_filename).unwrap().len()
+    );
+    assert!(record["duration_ms"].is_u64());
+    assert!(record["timestamp"].is_string());
+}
+
+#[test]
+fn compress_with_logfmt_log_format() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--log-format")
+        .arg("logfmt")
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("level=info"))
+        .stderr(predicate::str::contains("stage=compress"))
+        .stderr(predicate::str::contains(format!(
+            "original_size={}",
+            TEST_DATA.len()
+        )));
+}
+
+#[test]
+fn compress_with_json_log_format_and_skipped_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt.gz");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let output = utils::command::command()
+        .arg("--log-format")
+        .arg("json")
+        .arg(&input_filename)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let records = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let record = records
+        .iter()
+        .find(|record| record["level"] == "WARN")
+        .unwrap();
+    assert_eq!(record["file"], input_filename.display().to_string());
+    assert_eq!(record["stage"], "skip");
+    assert_eq!(
+        record["message"],
+        format!(
+            "{} already has .gz suffix, so it has been skipped",
+            input_filename.display()
+        )
+    );
+}
+
+#[test]
+fn compress_with_log_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let log_filename = temp_dir_path.join("rzopfli.log");
+    utils::command::command()
+        .arg("--log-file")
+        .arg(&log_filename)
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+    assert!(
+        fs::read_to_string(log_filename)
+            .unwrap()
+            .contains("Saving to:")
+    );
+}
+
+#[test]
+fn compress_with_invalid_log_format() {
+    utils::command::command()
+        .arg("--log-format")
+        .arg("a")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'a' for '--log-format <FORMAT>'",
+        ));
+}
+
+#[test]
+fn compress_with_json_log_format_and_errors() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    fs::write(&input_filenames[0], TEST_DATA).unwrap();
+    let output = utils::command::command()
+        .arg("--log-format")
+        .arg("json")
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    // Every line, including errors, is a JSON object.
+    let records = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let record = records
+        .iter()
+        .find(|record| record["level"] == "ERROR" && record["stage"] == "compress")
+        .unwrap();
+    assert_eq!(record["file"], input_filenames[1].display().to_string());
+    assert!(record["message"].as_str().unwrap().starts_with(&format!(
+        "could not open {}: ",
+        input_filenames[1].display()
+    )));
+    let record = records
+        .iter()
+        .find(|record| record["stage"] == "exit")
+        .unwrap();
+    assert_eq!(record["level"], "ERROR");
+    assert_eq!(record["message"], "1 of 2 files could not be processed");
+}
+
+#[test]
+fn compress_with_log_file_and_error() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    let log_filename = temp_dir_path.join("rzopfli.log");
+    utils::command::command()
+        .arg("--log-file")
+        .arg(&log_filename)
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "Error: could not open {}",
+            input_filename.display()
+        )));
+    assert!(
+        fs::read_to_string(log_filename)
+            .unwrap()
+            .contains(&format!("could not open {}: ", input_filename.display()))
+    );
+}
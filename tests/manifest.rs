@@ -0,0 +1,168 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{fs, path::Path};
+
+use predicates::prelude::predicate;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+fn read_manifest(path: impl AsRef<Path>) -> Map<String, Value> {
+    serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn compress_with_manifest() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let manifest = temp_dir_path.join("manifest.json");
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--manifest")
+        .arg(&manifest)
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    let output_filename = temp_dir_path.join("foo.txt.gz");
+    let compressed_data = fs::read(&output_filename).unwrap();
+    let entries = read_manifest(&manifest);
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[input_filename.to_str().unwrap()];
+    assert_eq!(entry["output"], output_filename.to_str().unwrap());
+    assert_eq!(entry["original_size"], TEST_DATA.len());
+    assert_eq!(entry["compressed_size"], compressed_data.len());
+    assert_eq!(
+        entry["etag"],
+        format!("\"{:x}\"", Sha256::digest(&compressed_data))
+    );
+    assert_eq!(entry["format"], "gzip");
+    assert_eq!(entry["content_encoding"], "gzip");
+}
+
+#[test]
+fn compress_with_manifest_and_blake3() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let manifest = temp_dir_path.join("manifest.json");
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--manifest")
+        .arg(&manifest)
+        .arg("--checksum-algorithm")
+        .arg("blake3")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    // The `ETag` is always the SHA-256 digest.
+    let compressed_data = fs::read(temp_dir_path.join("foo.txt.gz")).unwrap();
+    assert_eq!(
+        read_manifest(&manifest)[input_filename.to_str().unwrap()]["etag"],
+        format!("\"{:x}\"", Sha256::digest(&compressed_data))
+    );
+}
+
+#[test]
+fn compress_with_manifest_merges_existing_one() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let manifest = temp_dir_path.join("manifest.json");
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, TEST_DATA).unwrap();
+        utils::command::command()
+            .arg("--manifest")
+            .arg(&manifest)
+            .arg("--format")
+            .arg("zlib")
+            .arg("-i")
+            .arg("1")
+            .arg(input_filename)
+            .assert()
+            .success();
+    }
+    let entries = read_manifest(&manifest);
+    assert_eq!(
+        entries.keys().collect::<Vec<_>>(),
+        [
+            input_filenames[1].to_str().unwrap(),
+            input_filenames[0].to_str().unwrap()
+        ]
+    );
+    assert_eq!(
+        entries[input_filenames[0].to_str().unwrap()]["format"],
+        "zlib"
+    );
+    assert_eq!(
+        entries[input_filenames[1].to_str().unwrap()]["content_encoding"],
+        "deflate"
+    );
+}
+
+#[test]
+fn precompress_with_manifest() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let root = temp_dir_path.join("public");
+    let manifest = temp_dir_path.join("manifest.json");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("foo.html"), TEST_DATA).unwrap();
+    fs::write(root.join("bar.css"), TEST_DATA).unwrap();
+    let precompress = || {
+        utils::command::command()
+            .arg("--static")
+            .arg(&root)
+            .arg("--manifest")
+            .arg(&manifest)
+            .arg("-i")
+            .arg("1")
+            .assert()
+            .success();
+    };
+    precompress();
+    assert_eq!(read_manifest(&manifest).len(), 2);
+
+    // The entries of up-to-date files are kept, and those of removed files are
+    // removed.
+    fs::remove_file(root.join("bar.css")).unwrap();
+    fs::remove_file(&manifest).unwrap();
+    precompress();
+    let entries = read_manifest(&manifest);
+    assert_eq!(
+        entries.keys().collect::<Vec<_>>(),
+        [root.join("foo.html").to_str().unwrap()]
+    );
+    assert_eq!(
+        entries[root.join("foo.html").to_str().unwrap()]["output"],
+        root.join("foo.html.gz").to_str().unwrap()
+    );
+}
+
+#[test]
+fn compress_with_invalid_manifest() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let manifest = temp_dir_path.join("manifest.json");
+    fs::write(&manifest, "{").unwrap();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    utils::command::command()
+        .arg("--manifest")
+        .arg(&manifest)
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .code(65)
+        .stderr(predicate::str::contains("could not read the manifest"));
+    assert!(!temp_dir_path.join("foo.txt.gz").exists());
+}
@@ -0,0 +1,105 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{fs, io::Read};
+
+use flate2::read::GzDecoder;
+use predicates::prelude::{PredicateBooleanExt, predicate};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_with_memory_limit() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let mut output_filename = input_filename.clone();
+    output_filename.as_mut_os_string().push(".gz");
+    utils::command::command()
+        .arg("--memory-limit")
+        .arg("32 MiB")
+        .arg(input_filename)
+        .assert()
+        .success();
+    let compressed_data = fs::read(output_filename).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+}
+
+#[test]
+fn compress_with_too_low_memory_limit() {
+    let output = utils::command::command()
+        .arg("--memory-limit")
+        .arg("1mib")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("the memory limit is too low")
+    );
+    let mut decoder = GzDecoder::new(output.stdout.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+}
+
+#[test]
+fn compress_with_memory_limit_shared_by_jobs() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, TEST_DATA).unwrap();
+    }
+    // 24 MiB is enough for a single job, but not for two jobs.
+    utils::command::command()
+        .arg("--memory-limit")
+        .arg("24 MiB")
+        .arg("-j")
+        .arg("2")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filenames[0])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("the memory limit is too low").not());
+    utils::command::command()
+        .arg("--memory-limit")
+        .arg("24 MiB")
+        .arg("-j")
+        .arg("2")
+        .arg("-i")
+        .arg("1")
+        .arg("-f")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "the memory limit is too low, at least 16 MiB per job will be used",
+        ));
+}
+
+#[test]
+fn compress_with_invalid_memory_limit() {
+    utils::command::command()
+        .arg("--memory-limit")
+        .arg("a")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'a' for '--memory-limit <SIZE>'",
+        ));
+}
@@ -243,6 +243,47 @@ fn compress_with_remove() {
     assert!(!input_filename.exists());
 }
 
+#[test]
+fn compress_with_remove_and_synchronous() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let mut output_filename = input_filename.clone();
+    output_filename.as_mut_os_string().push(".gz");
+    utils::command::command()
+        .arg("--rm")
+        .arg("--synchronous")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} has been removed",
+            input_filename.display()
+        )));
+    let compressed_data = fs::read(output_filename).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+    assert!(!input_filename.exists());
+}
+
+#[test]
+fn synchronous_requires_remove() {
+    utils::command::command()
+        .arg("--synchronous")
+        .arg("data/LICENSES/CC-BY-4.0.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the following required arguments were not provided",
+        ));
+}
+
 #[test]
 fn compress_with_keep_conflicts_with_remove() {
     let temp_dir = tempfile::tempdir().unwrap();
@@ -0,0 +1,182 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{
+    fs::{self, File},
+    io::Read,
+};
+
+use flate2::read::GzDecoder;
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_with_output() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let output_filename = temp_dir_path.join("bar.gz");
+    utils::command::command()
+        .arg("-o")
+        .arg(&output_filename)
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Saving to: {}",
+            output_filename.display()
+        )));
+    let compressed_data = fs::read(output_filename).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+    assert!(!temp_dir_path.join("foo.txt.gz").exists());
+}
+
+#[test]
+fn compress_from_stdin_with_output() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_filename = temp_dir.path().join("bar.gz");
+    let output = utils::command::command()
+        .arg("-o")
+        .arg(&output_filename)
+        .write_stdin(TEST_DATA)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let compressed_data = fs::read(output_filename).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+}
+
+#[test]
+fn compress_with_output_and_existing_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let output_filename = temp_dir_path.join("bar.gz");
+    File::create_new(&output_filename).unwrap();
+    utils::command::command()
+        .arg("-o")
+        .arg(&output_filename)
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .code(73)
+        .stderr(predicate::str::contains(format!(
+            "could not open {}",
+            output_filename.display()
+        )));
+    utils::command::command()
+        .arg("-f")
+        .arg("-o")
+        .arg(&output_filename)
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert_ne!(fs::metadata(output_filename).unwrap().len(), 0);
+}
+
+#[test]
+fn compress_with_output_and_multiple_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = [temp_dir_path.join("foo.txt"), temp_dir_path.join("bar.txt")];
+    for input_filename in &input_filenames {
+        fs::write(input_filename, TEST_DATA).unwrap();
+    }
+    utils::command::command()
+        .arg("-o")
+        .arg(temp_dir_path.join("baz.gz"))
+        .args(input_filenames)
+        .assert()
+        .failure()
+        .code(64)
+        .stderr(predicate::str::contains(
+            "the argument '--output <FILE>' cannot be used with multiple input files",
+        ));
+}
+
+#[test]
+fn compress_with_output_dir() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_dir = temp_dir_path.join("src/foo");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("bar.txt"), TEST_DATA).unwrap();
+    utils::command::command()
+        .current_dir(temp_dir_path)
+        .arg("--output-dir")
+        .arg("out")
+        .arg("src/foo/bar.txt")
+        .assert()
+        .success();
+    let compressed_data = fs::read(temp_dir_path.join("out/bar.txt.gz")).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+    assert!(!input_dir.join("bar.txt.gz").exists());
+}
+
+#[test]
+fn compress_with_output_dir_and_mirror() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_dir = temp_dir_path.join("src/foo");
+    fs::create_dir_all(&input_dir).unwrap();
+    fs::write(input_dir.join("bar.txt"), TEST_DATA).unwrap();
+    utils::command::command()
+        .current_dir(temp_dir_path)
+        .arg("--output-dir")
+        .arg("out")
+        .arg("--mirror")
+        .arg("src/foo/bar.txt")
+        .assert()
+        .success();
+    let compressed_data = fs::read(temp_dir_path.join("out/src/foo/bar.txt.gz")).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+}
+
+#[test]
+fn compress_with_mirror_and_parent_dir() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    fs::create_dir(temp_dir_path.join("foo")).unwrap();
+    fs::write(temp_dir_path.join("bar.txt"), TEST_DATA).unwrap();
+    utils::command::command()
+        .current_dir(temp_dir_path.join("foo"))
+        .arg("--output-dir")
+        .arg("out")
+        .arg("--mirror")
+        .arg("../bar.txt")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("contains `..`"));
+}
+
+#[test]
+fn mirror_requires_output_dir() {
+    utils::command::command()
+        .arg("--mirror")
+        .arg("foo.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the following required arguments were not provided",
+        ));
+}
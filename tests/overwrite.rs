@@ -0,0 +1,165 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_with_overwrite_never() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    for input_filename in &input_filenames {
+        fs::write(input_filename, TEST_DATA).unwrap();
+    }
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    utils::command::command()
+        .arg("--overwrite")
+        .arg("never")
+        .arg("-i")
+        .arg("1")
+        .args(&input_filenames)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("is not overwritten"))
+        .stderr(predicate::str::contains(
+            "1 files have been compressed, 1 files have been skipped, 0 files could not be processed",
+        ));
+    assert_eq!(fs::read(temp_dir_path.join("foo.txt.gz")).unwrap(), b"foo");
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("bar.txt.gz")),
+        TEST_DATA
+    );
+}
+
+#[test]
+fn compress_with_overwrite_ask_without_terminal() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    utils::command::command()
+        .arg("--overwrite")
+        .arg("ask")
+        .arg(&input_filename)
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("is not overwritten"));
+    assert_eq!(fs::read(temp_dir_path.join("foo.txt.gz")).unwrap(), b"foo");
+}
+
+#[test]
+fn compress_with_overwrite_always() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    utils::command::command()
+        .arg("--overwrite")
+        .arg("always")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.gz")),
+        TEST_DATA
+    );
+}
+
+#[test]
+fn compress_with_overwrite_backup() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    utils::command::command()
+        .arg("--overwrite")
+        .arg("backup")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.gz")),
+        TEST_DATA
+    );
+    assert_eq!(fs::read(temp_dir_path.join("foo.txt.gz~")).unwrap(), b"foo");
+}
+
+#[test]
+fn compress_with_numbered_backup() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    for _ in 0..2 {
+        utils::command::command()
+            .arg("--overwrite")
+            .arg("backup")
+            .arg("--backup")
+            .arg("numbered")
+            .arg("-i")
+            .arg("1")
+            .arg(&input_filename)
+            .assert()
+            .success();
+    }
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.gz")),
+        TEST_DATA
+    );
+    assert_eq!(
+        fs::read(temp_dir_path.join("foo.txt.gz.~1~")).unwrap(),
+        b"foo"
+    );
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.gz.~2~")),
+        TEST_DATA
+    );
+    assert!(!temp_dir_path.join("foo.txt.gz~").exists());
+}
+
+#[test]
+fn compress_with_overwrite_rename() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    fs::write(temp_dir_path.join("foo.txt.1.gz"), b"bar").unwrap();
+    utils::command::command()
+        .arg("--overwrite")
+        .arg("rename")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Saving to: {}",
+            temp_dir_path.join("foo.txt.2.gz").display()
+        )));
+    assert_eq!(fs::read(temp_dir_path.join("foo.txt.gz")).unwrap(), b"foo");
+    assert_eq!(
+        fs::read(temp_dir_path.join("foo.txt.1.gz")).unwrap(),
+        b"bar"
+    );
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.2.gz")),
+        TEST_DATA
+    );
+}
@@ -0,0 +1,107 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[cfg(unix)]
+#[test]
+fn compress_with_nice() {
+    let output = utils::command::command()
+        .arg("--nice")
+        .arg("19")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut decoder = GzDecoder::new(output.stdout.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+}
+
+#[test]
+fn compress_with_invalid_nice() {
+    utils::command::command()
+        .arg("--nice")
+        .arg("20")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value '20' for '--nice <N>'",
+        ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn compress_with_io_class() {
+    let output = utils::command::command()
+        .arg("--io-class")
+        .arg("idle")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut decoder = GzDecoder::new(output.stdout.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+}
+
+#[test]
+fn compress_with_invalid_io_class() {
+    utils::command::command()
+        .arg("--io-class")
+        .arg("realtime")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'realtime' for '--io-class <CLASS>'",
+        ));
+}
+
+#[test]
+fn compress_with_cpu_limit() {
+    let output = utils::command::command()
+        .arg("--cpu-limit")
+        .arg("50")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut decoder = GzDecoder::new(output.stdout.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+}
+
+#[test]
+fn compress_with_invalid_cpu_limit() {
+    utils::command::command()
+        .arg("--cpu-limit")
+        .arg("0")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value '0' for '--cpu-limit <PERCENT>'",
+        ));
+}
@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![cfg(unix)]
+
+use std::{
+    fs,
+    path::Path,
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+fn spawn_and_wait_for(args: &[&str], input_filename: &Path, output_filename: &Path) -> Child {
+    let child = Command::new(env!("CARGO_BIN_EXE_rzopfli"))
+        .args(args)
+        .arg(input_filename)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    while !output_filename.exists() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    child
+}
+
+fn kill(child: &Child, signal: &str) {
+    let status = Command::new("kill")
+        .arg("-s")
+        .arg(signal)
+        .arg(child.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn interrupt_compression() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA.repeat(16)).unwrap();
+    let output_filename = temp_dir_path.join("foo.txt.gz");
+    let mut child = spawn_and_wait_for(&["--rm", "-i", "10000"], &input_filename, &output_filename);
+    kill(&child, "INT");
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+    assert!(input_filename.exists());
+    assert!(!output_filename.exists());
+}
+
+#[test]
+fn terminate_compression() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA.repeat(16)).unwrap();
+    let output_filename = temp_dir_path.join("out.gz");
+    let mut child = spawn_and_wait_for(
+        &[
+            "--rm",
+            "-i",
+            "10000",
+            "-o",
+            output_filename.to_str().unwrap(),
+        ],
+        &input_filename,
+        &output_filename,
+    );
+    kill(&child, "TERM");
+    assert_eq!(child.wait().unwrap().code(), Some(143));
+    assert!(input_filename.exists());
+    assert!(!output_filename.exists());
+}
+
+#[test]
+fn compress_to_closed_pipe() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rzopfli"))
+        .arg("-c")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let stdin = child.stdin.take().unwrap();
+    thread::spawn(move || {
+        let mut stdin = stdin;
+        let _ = std::io::Write::write_all(&mut stdin, TEST_DATA);
+    });
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(141));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn interrupt_compression_with_jobserver() {
+    use std::{fs::File, io::Read, io::Write, sync::mpsc};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let fifo = temp_dir_path.join("jobserver");
+    assert!(
+        Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap()
+            .success()
+    );
+    let mut jobserver = File::options().read(true).write(true).open(&fifo).unwrap();
+    jobserver.write_all(b"+").unwrap();
+    let input_filenames = ["foo.txt", "bar.txt"].map(|f| temp_dir_path.join(f));
+    // The files are different, so that they are compressed in parallel.
+    for (i, input_filename) in input_filenames.iter().enumerate() {
+        fs::write(
+            input_filename,
+            [
+                &TEST_DATA.repeat(16),
+                &[b'0' + u8::try_from(i).unwrap()][..],
+            ]
+            .concat(),
+        )
+        .unwrap();
+    }
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rzopfli"))
+        .env(
+            "MAKEFLAGS",
+            format!(" -j2 --jobserver-auth=fifo:{}", fifo.display()),
+        )
+        .args(["-j", "2", "-i", "10000"])
+        .args(&input_filenames)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Both files are being compressed, so the token has been acquired.
+    while !input_filenames
+        .iter()
+        .all(|f| f.with_extension("txt.gz").exists())
+    {
+        thread::sleep(Duration::from_millis(10));
+    }
+    kill(&child, "INT");
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+
+    // The token should be given back.
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut token = [u8::default()];
+        jobserver.read_exact(&mut token).unwrap();
+        sender.send(token).unwrap();
+    });
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap(),
+        *b"+"
+    );
+}
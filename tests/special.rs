@@ -0,0 +1,127 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::fs;
+
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn skip_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    utils::command::command()
+        .arg(temp_dir_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} is a directory, so it has been skipped",
+            temp_dir_path.display()
+        )));
+}
+
+#[cfg(unix)]
+#[test]
+fn skip_non_regular_file() {
+    utils::command::command()
+        .arg("/dev/null")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "/dev/null is not a regular file, so it has been skipped",
+        ));
+}
+
+#[cfg(unix)]
+#[test]
+fn skip_symlink() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(temp_dir_path.join("bar.txt"), TEST_DATA).unwrap();
+    std::os::unix::fs::symlink("bar.txt", &input_filename).unwrap();
+    utils::command::command()
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} is a symbolic link, so it has been skipped",
+            input_filename.display()
+        )));
+    assert!(!temp_dir_path.join("foo.txt.gz").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn compress_symlink_with_follow_symlinks() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(temp_dir_path.join("bar.txt"), TEST_DATA).unwrap();
+    std::os::unix::fs::symlink("bar.txt", &input_filename).unwrap();
+    utils::command::command()
+        .arg("--follow-symlinks")
+        .arg("--rm")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert!(temp_dir_path.join("foo.txt.gz").exists());
+    assert!(fs::symlink_metadata(&input_filename).is_err());
+    assert!(!temp_dir_path.join("bar.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn skip_hard_link_with_remove() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    fs::hard_link(&input_filename, temp_dir_path.join("bar.txt")).unwrap();
+    utils::command::command()
+        .arg("--rm")
+        .arg(&input_filename)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} has 1 other links, so it has been skipped",
+            input_filename.display()
+        )));
+    assert!(input_filename.exists());
+    assert!(!temp_dir_path.join("foo.txt.gz").exists());
+
+    utils::command::command()
+        .arg("--rm")
+        .arg("-f")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert!(!input_filename.exists());
+    assert!(temp_dir_path.join("foo.txt.gz").exists());
+    assert_eq!(fs::read(temp_dir_path.join("bar.txt")).unwrap(), TEST_DATA);
+}
+
+#[cfg(unix)]
+#[test]
+fn compress_hard_link_without_remove() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    fs::hard_link(&input_filename, temp_dir_path.join("bar.txt")).unwrap();
+    utils::command::command()
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    assert!(temp_dir_path.join("foo.txt.gz").exists());
+}
@@ -0,0 +1,135 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use flate2::read::GzDecoder;
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+fn modified(path: impl AsRef<Path>) -> SystemTime {
+    fs::metadata(path).unwrap().modified().unwrap()
+}
+
+#[test]
+fn precompress() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir(root.join("js")).unwrap();
+    fs::write(root.join("index.html"), TEST_DATA).unwrap();
+    fs::write(root.join("js/main.js"), TEST_DATA).unwrap();
+    fs::write(root.join("style.css"), b"body {}\n").unwrap();
+    fs::write(root.join("image.png"), TEST_DATA).unwrap();
+    fs::write(root.join("old.js.gz"), b"").unwrap();
+    fs::write(root.join("archive.tar.gz"), b"").unwrap();
+    utils::command::command()
+        .arg("--static")
+        .arg(root)
+        .arg("--min-size")
+        .arg("1 KiB")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "2 files have been compressed, 1 files have been skipped, 1 stale files have been removed",
+        ));
+
+    for source in ["index.html", "js/main.js"] {
+        let source = root.join(source);
+        let mut output = source.clone();
+        output.as_mut_os_string().push(".gz");
+        let compressed_data = fs::read(&output).unwrap();
+        let mut decoder = GzDecoder::new(compressed_data.as_slice());
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, TEST_DATA);
+        assert_eq!(modified(output), modified(&source));
+        assert!(source.exists());
+    }
+    assert!(!root.join("style.css.gz").exists());
+    assert!(!root.join("image.png.gz").exists());
+    assert!(!root.join("old.js.gz").exists());
+    assert!(root.join("archive.tar.gz").exists());
+}
+
+#[test]
+fn precompress_only_out_of_date_files() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path();
+    let source = root.join("index.html");
+    let output = root.join("index.html.gz");
+    fs::write(&source, TEST_DATA).unwrap();
+    fs::write(&output, b"foo").unwrap();
+    File::options()
+        .write(true)
+        .open(&output)
+        .unwrap()
+        .set_modified(modified(&source))
+        .unwrap();
+    utils::command::command()
+        .arg("--static")
+        .arg(root)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "0 files have been compressed, 1 files have been skipped, 0 stale files have been removed",
+        ));
+    assert_eq!(fs::read(&output).unwrap(), b"foo");
+
+    File::options()
+        .write(true)
+        .open(&source)
+        .unwrap()
+        .set_modified(modified(&output) + Duration::from_secs(1))
+        .unwrap();
+    utils::command::command()
+        .arg("--static")
+        .arg(root)
+        .arg("--static-ext")
+        .arg(".HTML")
+        .assert()
+        .success();
+    let compressed_data = fs::read(&output).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = Vec::new();
+    decoder.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+    assert_eq!(modified(output), modified(source));
+}
+
+#[test]
+fn precompress_with_input() {
+    utils::command::command()
+        .arg("--static")
+        .arg("data")
+        .arg("foo.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the argument '--static <DIR>' cannot be used with '[FILE]...'",
+        ));
+}
+
+#[test]
+fn compress_with_static_ext_without_static() {
+    // Unlike --static-ext, --min-size can also be used without --static.
+    utils::command::command()
+        .arg("--static-ext")
+        .arg("html")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the following required arguments were not provided",
+        ));
+}
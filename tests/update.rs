@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{
+    fs::{self, File},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use predicates::prelude::{PredicateBooleanExt, predicate};
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+fn set_modified(path: impl AsRef<Path>, time: SystemTime) {
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(time)
+        .unwrap();
+}
+
+#[test]
+fn compress_with_update() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let now = SystemTime::now();
+    for input_filename in ["foo.txt", "bar.txt", "baz.txt"] {
+        fs::write(temp_dir_path.join(input_filename), TEST_DATA).unwrap();
+        set_modified(temp_dir_path.join(input_filename), now);
+    }
+    // The output file of "foo.txt" is out of date, and that of "bar.txt" is up
+    // to date.
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    set_modified(
+        temp_dir_path.join("foo.txt.gz"),
+        now - Duration::from_secs(60),
+    );
+    fs::write(temp_dir_path.join("bar.txt.gz"), b"bar").unwrap();
+    set_modified(temp_dir_path.join("bar.txt.gz"), now);
+    utils::command::command()
+        .arg("-u")
+        .arg(temp_dir_path.join("foo.txt"))
+        .arg(temp_dir_path.join("bar.txt"))
+        .arg(temp_dir_path.join("baz.txt"))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{} is up to date",
+            temp_dir_path.join("bar.txt.gz").display()
+        )))
+        .stderr(predicate::str::contains(
+            "2 files have been compressed, 1 files have been skipped, 0 files could not be processed",
+        ));
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("foo.txt.gz")),
+        TEST_DATA
+    );
+    assert_eq!(fs::read(temp_dir_path.join("bar.txt.gz")).unwrap(), b"bar");
+    assert_eq!(
+        utils::gzip::decompress(temp_dir_path.join("baz.txt.gz")),
+        TEST_DATA
+    );
+}
+
+#[test]
+fn compress_with_update_and_recorded_checksum() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let output_filename = temp_dir_path.join("foo.txt.gz");
+    let checksum_filename = temp_dir_path.join("SHA256SUMS");
+    let command = || {
+        let mut command = utils::command::command();
+        command
+            .arg("--update")
+            .arg("--checksum-file")
+            .arg(&checksum_filename)
+            .arg("--checksum-input")
+            .arg(&input_filename);
+        command
+    };
+    command().assert().success();
+    let checksums = fs::read_to_string(&checksum_filename).unwrap();
+
+    // The input file is touched, but its content is not changed.
+    set_modified(&input_filename, SystemTime::now() + Duration::from_secs(60));
+    command()
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("is up to date"))
+        .stderr(predicate::str::contains("Saving to:").not());
+    assert_eq!(fs::read_to_string(&checksum_filename).unwrap(), checksums);
+
+    fs::write(&input_filename, b"Hello, world!\n").unwrap();
+    set_modified(
+        &input_filename,
+        SystemTime::now() + Duration::from_secs(120),
+    );
+    command()
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Saving to:"));
+    assert_eq!(utils::gzip::decompress(output_filename), b"Hello, world!\n");
+    assert_ne!(fs::read_to_string(checksum_filename).unwrap(), checksums);
+}
+
+#[test]
+fn update_conflicts_with_stdout() {
+    utils::command::command()
+        .arg("-u")
+        .arg("-c")
+        .arg("data/LICENSES/CC-BY-4.0.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the argument '--update' cannot be used with '--stdout'",
+        ));
+}
+
+#[test]
+fn compress_with_update_reports_error_of_only_pending_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let now = SystemTime::now();
+    for input_filename in ["foo.txt", "bar.txt"] {
+        fs::write(temp_dir_path.join(input_filename), TEST_DATA).unwrap();
+        set_modified(temp_dir_path.join(input_filename), now);
+    }
+    // The output file of "foo.txt" is up to date, and that of "bar.txt" cannot
+    // be written.
+    fs::write(temp_dir_path.join("foo.txt.gz"), b"foo").unwrap();
+    set_modified(temp_dir_path.join("foo.txt.gz"), now);
+    fs::create_dir(temp_dir_path.join("bar.txt.gz")).unwrap();
+    utils::command::command()
+        .arg("-u")
+        .arg("-i")
+        .arg("1")
+        .arg(temp_dir_path.join("foo.txt"))
+        .arg(temp_dir_path.join("bar.txt"))
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(format!(
+            "could not open {}",
+            temp_dir_path.join("bar.txt.gz").display()
+        )))
+        .stderr(predicate::str::contains(
+            "Error: 1 of 2 files could not be processed",
+        ));
+}
+
+#[test]
+fn compress_with_update_and_directory_as_output() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let now = SystemTime::now();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    set_modified(&input_filename, now - Duration::from_secs(60));
+    fs::create_dir(temp_dir_path.join("foo.txt.gz")).unwrap();
+    utils::command::command()
+        .arg("-u")
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is up to date").not());
+    assert!(temp_dir_path.join("foo.txt.gz").is_dir());
+}
@@ -0,0 +1,27 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+
+/// Returns `data` compressed into the gzip format.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Returns the decompressed data of the gzip file at `path`.
+pub fn decompress(path: impl AsRef<Path>) -> Vec<u8> {
+    let compressed_data = fs::read(path).unwrap();
+    let mut decoder = GzDecoder::new(compressed_data.as_slice());
+    let mut buf = Vec::new();
+    decoder.read_to_end(&mut buf).unwrap();
+    buf
+}
@@ -3,3 +3,6 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 pub mod command;
+// Not every test uses these helpers.
+#[allow(dead_code)]
+pub mod gzip;
@@ -0,0 +1,115 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use std::{fs, io::Read};
+
+use flate2::read::ZlibDecoder;
+use predicates::prelude::predicate;
+
+const TEST_DATA: &[u8] = include_bytes!("data/LICENSES/CC-BY-4.0.txt");
+
+#[test]
+fn compress_with_verify() {
+    for format in ["gzip", "zlib", "deflate"] {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_dir_path = temp_dir.path();
+        let input_filename = temp_dir_path.join("foo.txt");
+        fs::write(&input_filename, TEST_DATA).unwrap();
+        let output_filename = temp_dir_path.join("foo.out");
+        utils::command::command()
+            .arg("--verify")
+            .arg("--format")
+            .arg(format)
+            .arg("-o")
+            .arg(&output_filename)
+            .arg(&input_filename)
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(format!(
+                "{} has been verified",
+                output_filename.display()
+            )));
+        assert!(output_filename.exists());
+    }
+}
+
+#[test]
+fn compress_with_verify_and_remove() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    let mut output_filename = input_filename.clone();
+    output_filename.as_mut_os_string().push(".zlib");
+    utils::command::command()
+        .arg("--verify")
+        .arg("--rm")
+        .arg("--format")
+        .arg("zlib")
+        .arg(&input_filename)
+        .assert()
+        .success();
+    let compressed_data = fs::read(output_filename).unwrap();
+    let mut decoder = ZlibDecoder::new(compressed_data.as_slice());
+    let mut buf = vec![u8::default(); TEST_DATA.len()];
+    decoder.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, TEST_DATA);
+    assert!(!input_filename.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn compress_with_verify_failure() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    let input_filename = temp_dir_path.join("foo.txt");
+    fs::write(&input_filename, TEST_DATA).unwrap();
+    // The compressed data is discarded, so nothing can be decompressed.
+    let output_filename = temp_dir_path.join("foo.txt.gz");
+    std::os::unix::fs::symlink("/dev/null", &output_filename).unwrap();
+    utils::command::command()
+        .arg("--verify")
+        .arg("--rm")
+        .arg("-f")
+        .arg("-i")
+        .arg("1")
+        .arg(&input_filename)
+        .assert()
+        .failure()
+        .code(65)
+        .stderr(predicate::str::contains(format!(
+            "could not verify {}",
+            output_filename.display()
+        )));
+    assert!(input_filename.exists());
+    assert!(fs::symlink_metadata(&output_filename).is_err());
+}
+
+#[test]
+fn compress_from_stdin_with_verify() {
+    utils::command::command()
+        .arg("--verify")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "could not verify data written to standard output",
+        ));
+}
+
+#[test]
+fn verify_conflicts_with_stdout() {
+    utils::command::command()
+        .arg("--verify")
+        .arg("-c")
+        .write_stdin(TEST_DATA)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the argument '--verify' cannot be used with '--stdout'",
+        ));
+}
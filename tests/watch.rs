@@ -0,0 +1,97 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+mod utils;
+
+use predicates::prelude::predicate;
+
+#[cfg(target_os = "linux")]
+fn wait_until(mut condition: impl FnMut() -> bool) {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    let start = Instant::now();
+    while !condition() {
+        assert!(
+            start.elapsed() < Duration::from_secs(60),
+            "timed out waiting for the condition"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    let mut buf = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn compress_with_watch() {
+    use std::{
+        fs,
+        process::{Command, Stdio},
+    };
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_dir_path = temp_dir.path();
+    fs::write(temp_dir_path.join("foo.txt"), b"foo").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rzopfli"))
+        .arg("--watch")
+        .arg(temp_dir_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let read_output = |name: &str| {
+        fs::read(temp_dir_path.join(name))
+            .ok()
+            .and_then(|data| decompress(&data))
+    };
+
+    // Existing files are compressed at startup.
+    wait_until(|| read_output("foo.txt.gz").as_deref() == Some(b"foo"));
+
+    // New files are compressed, including files in a new subdirectory.
+    fs::write(temp_dir_path.join("bar.txt"), b"bar").unwrap();
+    wait_until(|| read_output("bar.txt.gz").as_deref() == Some(b"bar"));
+    fs::create_dir(temp_dir_path.join("baz")).unwrap();
+    fs::write(temp_dir_path.join("baz/qux.txt"), b"qux").unwrap();
+    wait_until(|| read_output("baz/qux.txt.gz").as_deref() == Some(b"qux"));
+
+    // Changed files are compressed again.
+    fs::write(temp_dir_path.join("foo.txt"), b"Hello, world!").unwrap();
+    wait_until(|| read_output("foo.txt.gz").as_deref() == Some(b"Hello, world!"));
+
+    // Output files of removed files are removed.
+    fs::remove_file(temp_dir_path.join("bar.txt")).unwrap();
+    wait_until(|| !temp_dir_path.join("bar.txt.gz").exists());
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(!temp_dir_path.join("foo.txt.gz.gz").exists());
+}
+
+#[test]
+fn compress_with_watch_and_input() {
+    utils::command::command()
+        .arg("--watch")
+        .arg("data")
+        .arg("foo.txt")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the argument '--watch <DIR>' cannot be used with '[FILE]...'",
+        ));
+}